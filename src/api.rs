@@ -1,77 +1,950 @@
 use exonum::api::{self, ServiceApiBuilder, ServiceApiState};
+use exonum::api::backends::actix::{FutureResponse, HttpRequest, RequestHandler};
+use actix_web::{self, http::Method, ws};
+use futures::future;
+use serde_urlencoded;
+use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
 use exonum::crypto::{Hash, PublicKey};
-use exonum::blockchain::Transaction;
+use exonum::storage::Snapshot;
+use exonum::blockchain::{Schema, Transaction, TransactionErrorType, TransactionResult, TransactionSet};
 use exonum::node::TransactionSend;
+use exonum::encoding::serialize::{encode_hex, decode_hex};
 use schema as ex_schema;
-use transactions::AuctionTransactions;
+use transactions::{
+    AuctionTransactions, TxCreateLot, TxCreateBid, TxCloseLot, TxProposeConfig, TxVoteConfig, TxSuspendLot, TxDelistLot, TxBanMember,
+    TxSettleLot, TxRateCounterparty, rebuild_lot_txs, check_stateless, check_lot_state, dry_run
+};
+use media::{MediaConfig, Blob};
+use config::LocalSettings;
+use AUCTION_SERVICE_ID;
+use consistency::{ConsistencyReport, Inconsistency};
+use errors::{Error, ErrorInfo};
+use spec;
+use events::{AuctionEvent, EventHub, EventFilter, EventSession, block_events};
+use webhooks::{self, Webhook, WebhookQueue, RetryPolicy};
+use reqwest::Url;
+use juniper::http::GraphQLRequest;
+use graphql;
+use routes::{ApiRoutes, ApiVersion, VersionInfo};
 
 
 #[derive(Debug, Clone, Copy)]
 pub struct AuctionApi;
 
 
+// What the route tables of every version are built from.
+#[derive(Debug, Clone, Default)]
+pub struct ApiContext {
+    // From the node config; see `LocalSettings` for stored overrides.
+    pub settings: LocalSettings,
+    pub events: EventHub,
+    pub webhooks: WebhookQueue
+}
+
+
+pub const API_VERSIONS: &[ApiVersion] = &[
+    ApiVersion {
+        name: "v1",
+        deprecated: false,
+        // Everything served before the API was versioned.
+        legacy_aliases: &[
+            "lot", "bid", "lot_bids", "lots/batch", "bids/batch", "lot_history", "lot_txs", "stats", "close_lot", "media", "check_consistency"
+        ],
+        routes: AuctionApi::routes_v1
+    }
+];
+
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct LotQuery {
-    pub pub_key: PublicKey
+    pub pub_key: PublicKey,
+    pub height: Option<u64>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct BidQuery {
-    pub pub_key: PublicKey
+    pub pub_key: PublicKey,
+    pub height: Option<u64>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct LotBidsQuery {
+    pub lot_pub_key: PublicKey,
+    pub height: Option<u64>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LotHistoryQuery {
+    pub pub_key: PublicKey
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LotModerationQuery {
     pub lot_pub_key: PublicKey
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct SettlementQuery {
+    pub lot_pub_key: PublicKey
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ReputationQuery {
+    pub pub_key: PublicKey
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RatingsQuery {
+    pub pub_key: PublicKey
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemberRatings {
+    pub pub_key: PublicKey,
+    pub count: u64,
+    pub average: Option<f64>,
+    pub ratings: Vec<ex_schema::Rating>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemberReputation {
+    pub pub_key: PublicKey,
+    pub score: i64,
+    pub reputation: ex_schema::Reputation,
+    pub ban: Option<ex_schema::MemberBan>
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionResponse {
     pub tx_hash: Hash
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DryRunResponse {
+    pub tx_hash: Hash,
+    pub success: bool,
+    pub error: Option<ErrorInfo>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TxStatus {
+    Success,
+    Error { code: u8, description: Option<String> },
+    Panic { description: Option<String> }
+}
+
+impl<'a> From<&'a TransactionResult> for TxStatus {
+    fn from(result: &'a TransactionResult) -> Self {
+        match *result {
+            Ok(()) => TxStatus::Success,
+            Err(ref e) => {
+                let description = e.description().map(|d| d.to_owned());
+                match e.error_type() {
+                    TransactionErrorType::Code(code) => TxStatus::Error { code, description },
+                    TransactionErrorType::Panic => TxStatus::Panic { description }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct TxStatusQuery {
+    pub tx_hash: Hash
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxState {
+    Pending,
+    Committed,
+    Failed
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TransactionStatus {
+    pub tx_hash: Hash,
+    pub status: TxState,
+    pub lot_pub_key: Option<PublicKey>,
+    pub height: Option<u64>,
+    pub error: Option<ErrorInfo>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LotTxsQuery {
+    pub lot_pub_key: PublicKey
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LotTransaction {
+    pub tx_hash: Hash,
+    pub height: u64,
+    pub position: u64,
+    pub status: TxStatus,
+    pub content: AuctionTransactions
+}
+
+pub const TOP_SELLERS_LIMIT: usize = 10;
+pub const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchQuery {
+    pub pub_keys: Vec<PublicKey>,
+    pub height: Option<u64>,
+    #[serde(default)]
+    pub include_delisted: bool
+}
+
+// Delisted lots are neither found nor missing: unless `include_delisted` is set, their keys are listed apart.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchResponse<T> {
+    pub found: Vec<T>,
+    pub missing: Vec<PublicKey>,
+    #[serde(default)]
+    pub delisted: Vec<PublicKey>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct LotsQuery {
+    pub seller_pub_key: Option<PublicKey>,
+    pub after: Option<PublicKey>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub include_delisted: bool
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct StatsQuery {
+    pub from: Option<u64>,
+    pub to: Option<u64>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SellerVolume {
+    pub seller_pub_key: PublicKey,
+    pub lots: u64,
+    pub volume: u64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub from: u64,
+    pub to: u64,
+    pub open_lots: u64,
+    pub closed_lots: u64,
+    pub lots_created: u64,
+    pub lots_closed: u64,
+    pub lots_sold: u64,
+    pub bids: u64,
+    pub volume: u64,
+    pub average_price: f64,
+    pub average_reserve: f64,
+    pub bids_per_lot: BTreeMap<u64, u64>,
+    pub top_sellers: Vec<SellerVolume>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct MediaQuery {
+    pub hash: Hash
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Media {
+    pub mime_type: String,
+    pub data: String
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaResponse {
+    pub hash: Hash
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct WebhookQuery {
+    pub id: Hash
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookEntry {
+    pub id: Hash,
+    pub webhook: Webhook
+}
+
+// Both heights are inclusive.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RedeliverQuery {
+    pub from_height: u64,
+    pub to_height: u64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RedeliverReport {
+    pub events: u64,
+    pub queued: u64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    pub tx_hash: Hash,
+    pub content: AuctionTransactions
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SchemaStats {
+    pub lots: u64,
+    pub bids: u64,
+    pub lot_txs: u64,
+    pub lot_outcomes: u64,
+    pub stats_snapshots: u64,
+    pub media: u64,
+    pub blobs: u64,
+    pub webhooks: u64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildReport {
+    pub lot_txs: u64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConfigProposalInfo {
+    pub proposal_hash: Hash,
+    pub proposer: PublicKey,
+    pub config: ex_schema::ServiceConfig,
+    pub actual_from: u64,
+    pub votes: Vec<PublicKey>,
+    pub required_votes: u64,
+    pub accepted: bool
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub height: u64,
+    pub proposal_hash: Hash,
+    pub config: ex_schema::ServiceConfig
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeSettings {
+    pub media: MediaConfig,
+    pub webhook_retry: RetryPolicy,
+    pub webhooks: usize,
+    // Whether the settings come from an override stored through the API rather than the node config.
+    pub overridden: bool
+}
+
 
 impl AuctionApi {
     pub fn get_lot(state: &ServiceApiState, query: LotQuery) -> api::Result<ex_schema::Lot> {
         let snapshot = state.snapshot();
         let schema = ex_schema::AuctionSchema::new(snapshot);
-        schema.lot(&query.pub_key)
-            .ok_or_else(|| api::Error::NotFound("\"Lot is not found\"".to_owned()))
+        let lot = match query.height {
+            Some(height) => schema.lot_at(&query.pub_key, height),
+            None => schema.lot(&query.pub_key)
+        };
+        lot.ok_or_else(|| Error::LotNotFound.with("lot_pub_key", query.pub_key).into())
     }
 
     pub fn get_bid(state: &ServiceApiState, query: BidQuery) -> api::Result<ex_schema::Bid> {
         let snapshot = state.snapshot();
         let schema = ex_schema::AuctionSchema::new(snapshot);
-        schema.bid(&query.pub_key)
-            .ok_or_else(|| api::Error::NotFound("\"Bid is not found\"".to_owned()))
+        let bid = match query.height {
+            Some(height) => schema.bid_at(&query.pub_key, height),
+            None => schema.bid(&query.pub_key)
+        };
+        bid.ok_or_else(|| Error::BidNotFound.with("bid_pub_key", query.pub_key).into())
+    }
+
+    pub fn get_config(state: &ServiceApiState, _query: ()) -> api::Result<ex_schema::ServiceConfig> {
+        Ok(ex_schema::AuctionSchema::new(state.snapshot()).config())
+    }
+
+    fn config_proposal_info<T: AsRef<dyn Snapshot>>(schema: &ex_schema::AuctionSchema<T>, proposal_hash: Hash) -> api::Result<ConfigProposalInfo> {
+        let proposal = schema.config_proposals().get(&proposal_hash)
+            .ok_or_else(|| Error::ProposalNotFound.with("proposal_hash", proposal_hash))?;
+        let config = ::serde_json::from_str(proposal.config())
+            .map_err(|e| api::Error::InternalError(e.into()))?;
+        Ok(ConfigProposalInfo {
+            proposal_hash,
+            proposer: *proposal.proposer(),
+            config,
+            actual_from: proposal.actual_from(),
+            votes: schema.config_votes(&proposal_hash).iter().collect(),
+            required_votes: schema.config().required_votes(),
+            accepted: proposal.accepted()
+        })
+    }
+
+    pub fn get_pending_config(state: &ServiceApiState, _query: ()) -> api::Result<Option<ConfigProposalInfo>> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        match schema.pending_config() {
+            Some(proposal_hash) => Self::config_proposal_info(&schema, proposal_hash).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    pub fn get_config_proposals(state: &ServiceApiState, _query: ()) -> api::Result<Vec<ConfigProposalInfo>> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        let proposal_hashes: Vec<Hash> = schema.config_proposals().keys().collect();
+        proposal_hashes.into_iter().map(|proposal_hash| Self::config_proposal_info(&schema, proposal_hash)).collect()
+    }
+
+    pub fn get_config_history(state: &ServiceApiState, _query: ()) -> api::Result<Vec<ConfigChange>> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        let history = schema.config_history();
+        history.iter().map(|record| Ok(ConfigChange {
+            height: record.height(),
+            proposal_hash: *record.proposal_hash(),
+            config: ::serde_json::from_str(record.config()).map_err(|e| api::Error::InternalError(e.into()))?
+        })).collect()
+    }
+
+    pub fn get_lot_moderation(state: &ServiceApiState, query: LotModerationQuery) -> api::Result<Option<ex_schema::LotModeration>> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        if schema.lot(&query.lot_pub_key).is_none() {
+            return Err(Error::LotNotFound.with("lot_pub_key", query.lot_pub_key).into())
+        }
+        Ok(schema.lot_moderation().get(&query.lot_pub_key))
+    }
+
+    pub fn get_settlement(state: &ServiceApiState, query: SettlementQuery) -> api::Result<Option<ex_schema::Settlement>> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        if schema.lot(&query.lot_pub_key).is_none() {
+            return Err(Error::LotNotFound.with("lot_pub_key", query.lot_pub_key).into())
+        }
+        Ok(schema.settlements().get(&query.lot_pub_key))
+    }
+
+    // Unknown keys get a zero reputation rather than 404, since any key may become a member.
+    pub fn get_reputation(state: &ServiceApiState, query: ReputationQuery) -> api::Result<MemberReputation> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        let reputation = schema.reputation(&query.pub_key);
+        Ok(MemberReputation {
+            pub_key: query.pub_key,
+            score: reputation.score(),
+            reputation,
+            ban: schema.member_bans().get(&query.pub_key)
+        })
+    }
+
+    pub fn get_ratings(state: &ServiceApiState, query: RatingsQuery) -> api::Result<MemberRatings> {
+        let schema = ex_schema::AuctionSchema::new(state.snapshot());
+        let ratings: Vec<ex_schema::Rating> = schema.member_ratings(&query.pub_key).iter().collect();
+        Ok(MemberRatings {
+            pub_key: query.pub_key,
+            count: ratings.len() as u64,
+            average: schema.average_rating(&query.pub_key),
+            ratings
+        })
     }
 
     pub fn get_lot_bids(state: &ServiceApiState, query: LotBidsQuery) -> api::Result<Vec<ex_schema::Bid>> {
         let snapshot = state.snapshot();
         let schema = ex_schema::AuctionSchema::new(snapshot);
-        let lot = schema.lot(&query.lot_pub_key)
-            .ok_or_else(|| api::Error::NotFound("\"Lot is not found\"".to_owned()));
-        if let Err(e) = lot {return Err(e)}
-        let lot_bids = schema.lot_bids(&query.lot_pub_key);
-        let bids: Vec<ex_schema::Bid> = lot_bids.iter().map(|pub_key| schema.bid(&pub_key).unwrap()).collect();
+        let lot = match query.height {
+            Some(height) => schema.lot_at(&query.lot_pub_key, height),
+            None => schema.lot(&query.lot_pub_key)
+        };
+        if lot.is_none() {
+            return Err(Error::LotNotFound.with("lot_pub_key", query.lot_pub_key).into())
+        }
+        let bids = match query.height {
+            Some(height) => {
+                let lot_bids = schema.lot_bids(&query.lot_pub_key);
+                lot_bids.iter().filter_map(|pub_key| schema.bid_at(&pub_key, height)).collect()
+            },
+            None => schema.lot_bids_checked(&query.lot_pub_key).map_err(Self::inconsistency_error)?
+        };
         Ok(bids)
     }
 
-    pub fn post_transaction(state: &ServiceApiState, query: AuctionTransactions) -> api::Result<TransactionResponse> {
+    fn inconsistency_error(inconsistencies: Vec<Inconsistency>) -> api::Error {
+        Error::InconsistentState.with("inconsistencies", inconsistencies).into()
+    }
+
+    pub fn check_consistency(state: &ServiceApiState, _query: ()) -> api::Result<ConsistencyReport> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        Ok(schema.check_consistency())
+    }
+
+    // Lots in key order, a page at a time; the last key of a page is the `after` of the next one.
+    pub fn get_lots(state: &ServiceApiState, query: LotsQuery) -> api::Result<Vec<ex_schema::Lot>> {
+        let limit = query.limit.unwrap_or(graphql::DEFAULT_PAGE_SIZE as u64);
+        if limit > graphql::MAX_PAGE_SIZE as u64 {
+            return Err(Error::InvalidQuery.with("reason", "Page size is out of range").with("max_page_size", graphql::MAX_PAGE_SIZE).into())
+        }
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let page = |keys: &mut dyn Iterator<Item = PublicKey>| -> Vec<ex_schema::Lot> {
+            keys.filter(|pub_key| Some(*pub_key) != query.after)
+                .filter(|pub_key| query.include_delisted || !schema.is_delisted(pub_key))
+                .filter_map(|pub_key| schema.lot(&pub_key))
+                .take(limit as usize)
+                .collect()
+        };
+        Ok(match query.seller_pub_key {
+            Some(seller_pub_key) => {
+                let lots = schema.seller_lots(&seller_pub_key);
+                match query.after {
+                    Some(ref after) => page(&mut lots.iter_from(after)),
+                    None => page(&mut lots.iter())
+                }
+            }
+            None => {
+                let lots = schema.lots();
+                match query.after {
+                    Some(ref after) => page(&mut lots.keys_from(after)),
+                    None => page(&mut lots.keys())
+                }
+            }
+        })
+    }
+
+    pub fn get_lots_batch(state: &ServiceApiState, query: BatchQuery) -> api::Result<BatchResponse<ex_schema::Lot>> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let include_delisted = query.include_delisted;
+        Self::batch(query, |pub_key| !include_delisted && schema.is_delisted(pub_key), |pub_key, height| match height {
+            Some(height) => schema.lot_at(pub_key, height),
+            None => schema.lot(pub_key)
+        })
+    }
+
+    pub fn get_bids_batch(state: &ServiceApiState, query: BatchQuery) -> api::Result<BatchResponse<ex_schema::Bid>> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        Self::batch(query, |_| false, |pub_key, height| match height {
+            Some(height) => schema.bid_at(pub_key, height),
+            None => schema.bid(pub_key)
+        })
+    }
+
+    fn batch<T, D, F>(query: BatchQuery, delisted: D, lookup: F) -> api::Result<BatchResponse<T>>
+        where D: Fn(&PublicKey) -> bool, F: Fn(&PublicKey, Option<u64>) -> Option<T>
+    {
+        if query.pub_keys.len() > MAX_BATCH_SIZE {
+            return Err(Error::InvalidQuery.with("reason", "Batch is too large").with("max_batch_size", MAX_BATCH_SIZE).into())
+        }
+        let mut response = BatchResponse { found: Vec::new(), missing: Vec::new(), delisted: Vec::new() };
+        for pub_key in query.pub_keys {
+            if delisted(&pub_key) {
+                response.delisted.push(pub_key);
+                continue
+            }
+            match lookup(&pub_key, query.height) {
+                Some(value) => response.found.push(value),
+                None => response.missing.push(pub_key)
+            }
+        }
+        Ok(response)
+    }
+
+    pub fn get_lot_history(state: &ServiceApiState, query: LotHistoryQuery) -> api::Result<Vec<ex_schema::LotRecord>> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let history: Vec<ex_schema::LotRecord> = schema.lot_history(&query.pub_key).iter().collect();
+        if history.is_empty() {
+            return Err(Error::LotNotFound.with("lot_pub_key", query.pub_key).into())
+        }
+        Ok(history)
+    }
+
+    pub fn post_dry_run(state: &ServiceApiState, query: AuctionTransactions) -> api::Result<DryRunResponse> {
         let transaction: Box<dyn Transaction> = query.into();
         let tx_hash = transaction.hash();
+        let error = dry_run(transaction.as_ref(), state.blockchain().fork()).err();
+        Ok(DryRunResponse { tx_hash, success: error.is_none(), error })
+    }
+
+    pub fn post_transaction<T: Transaction>(state: &ServiceApiState, query: T) -> api::Result<TransactionResponse> {
+        check_stateless(&query)?;
+        check_lot_state(&query, state.snapshot().as_ref())?;
+        let transaction: Box<dyn Transaction> = Box::new(query);
+        let tx_hash = transaction.hash();
         state.sender().send(transaction)?;
         Ok(TransactionResponse { tx_hash })
     }
 
-    pub fn wire(builder: &mut ServiceApiBuilder) {
+    pub fn get_tx_status(state: &ServiceApiState, query: TxStatusQuery) -> api::Result<TransactionStatus> {
+        let snapshot = state.snapshot();
+        let core_schema = Schema::new(&snapshot);
+        let tx_hash = query.tx_hash;
+        let not_found = || Error::TransactionNotFound.with("tx_hash", tx_hash);
+        let raw = core_schema.transactions().get(&tx_hash)
+            .filter(|raw| raw.service_id() == AUCTION_SERVICE_ID)
+            .ok_or_else(not_found)?;
+        let content = AuctionTransactions::tx_from_raw(raw).map_err(|_| not_found())?;
+        let mut status = TransactionStatus {
+            tx_hash,
+            status: TxState::Pending,
+            lot_pub_key: content.lot_pub_key().cloned(),
+            height: None,
+            error: None
+        };
+        if core_schema.transactions_pool().contains(&tx_hash) {
+            return Ok(status)
+        }
+        let (location, result) = match (core_schema.transactions_locations().get(&tx_hash), core_schema.transaction_results().get(&tx_hash)) {
+            (Some(location), Some(result)) => (location, result),
+            _ => return Err(not_found().into())
+        };
+        status.height = Some(location.block_height().0);
+        status.status = match result {
+            Ok(()) => TxState::Committed,
+            Err(e) => {
+                status.error = Some(ErrorInfo::from(&e));
+                TxState::Failed
+            }
+        };
+        Ok(status)
+    }
+
+    pub fn get_lot_txs(state: &ServiceApiState, query: LotTxsQuery) -> api::Result<Vec<LotTransaction>> {
+        let snapshot = state.snapshot();
+        let core_schema = Schema::new(&snapshot);
+        let schema = ex_schema::AuctionSchema::new(&snapshot);
+        if schema.lot(&query.lot_pub_key).is_none() {
+            return Err(Error::LotNotFound.with("lot_pub_key", query.lot_pub_key).into())
+        }
+        let mut txs = Vec::new();
+        for tx_hash in schema.indexed_lot_txs(&query.lot_pub_key) {
+            let location = core_schema.transactions_locations().get(&tx_hash);
+            let result = core_schema.transaction_results().get(&tx_hash);
+            let raw = core_schema.transactions().get(&tx_hash);
+            if let (Some(location), Some(result), Some(raw)) = (location, result, raw) {
+                let content = AuctionTransactions::tx_from_raw(raw)
+                    .map_err(|e| api::Error::InternalError(e.to_string().into()))?;
+                txs.push(LotTransaction {
+                    tx_hash,
+                    height: location.block_height().0,
+                    position: location.position_in_block(),
+                    status: TxStatus::from(&result),
+                    content
+                });
+            }
+        }
+        Ok(txs)
+    }
+
+    pub fn get_stats(state: &ServiceApiState, query: StatsQuery) -> api::Result<StatsReport> {
+        let snapshot = state.snapshot();
+        let height = Schema::new(&snapshot).height().0;
+        let schema = ex_schema::AuctionSchema::new(&snapshot);
+        let from = query.from.unwrap_or(0);
+        let to = query.to.unwrap_or(height);
+        if from > to {
+            return Err(Error::InvalidQuery.with("reason", "Height window is empty").with("from", from).with("to", to).into())
+        }
+        let end = schema.stats_at(to);
+        let start = if from == 0 { ex_schema::AuctionStats::default() } else { schema.stats_at(from - 1) };
+        let lots_sold = end.sold_lots() - start.sold_lots();
+        let volume = end.volume() - start.volume();
+        let reserve = end.reserve() - start.reserve();
+        let average = |total: u64| if lots_sold == 0 { 0.0 } else { total as f64 / lots_sold as f64 };
+
+        let mut bids_per_lot = BTreeMap::new();
+        let mut sellers: HashMap<PublicKey, SellerVolume> = HashMap::new();
+        for outcome in schema.lot_outcomes_between(from, to) {
+            *bids_per_lot.entry(outcome.bids()).or_insert(0) += 1;
+            let seller = sellers.entry(*outcome.seller_pub_key()).or_insert_with(|| SellerVolume {
+                seller_pub_key: *outcome.seller_pub_key(), lots: 0, volume: 0
+            });
+            seller.lots += 1;
+            seller.volume += outcome.price();
+        }
+        let mut top_sellers: Vec<SellerVolume> = sellers.into_values().collect();
+        top_sellers.sort_by(|a, b| b.volume.cmp(&a.volume).then(b.lots.cmp(&a.lots)));
+        top_sellers.truncate(TOP_SELLERS_LIMIT);
+
+        Ok(StatsReport {
+            from,
+            to,
+            open_lots: end.open_lots(),
+            closed_lots: end.closed_lots(),
+            lots_created: end.lots() - start.lots(),
+            lots_closed: end.closed_lots() - start.closed_lots(),
+            lots_sold,
+            bids: end.bids() - start.bids(),
+            volume,
+            average_price: average(volume),
+            average_reserve: average(reserve),
+            bids_per_lot,
+            top_sellers
+        })
+    }
+
+    pub fn get_media(state: &ServiceApiState, query: MediaQuery) -> api::Result<Media> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let blob = schema.blob(&query.hash)
+            .ok_or_else(|| Error::MediaNotFound.with("hash", query.hash))?;
+        Ok(Media { mime_type: blob.mime_type().to_owned(), data: encode_hex(blob.data()) })
+    }
+
+    pub fn post_media(state: &ServiceApiState, query: Media, defaults: &LocalSettings) -> api::Result<MediaResponse> {
+        let data = decode_hex(&query.data)
+            .map_err(|_| Error::MediaRejected.with("reason", "Media data is not valid hex"))?;
+        let settings = defaults.effective(&ex_schema::AuctionSchema::new(state.snapshot()));
+        settings.media.check(&query.mime_type, &data)
+            .map_err(|reason| Error::MediaRejected.with("reason", reason))?;
+        let blob = Blob::new(&query.mime_type, data);
+        let hash = blob.content_hash();
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        ex_schema::AuctionSchema::new(&mut fork).blobs_mut().put(&hash, blob);
+        blockchain.merge(fork.into_patch())?;
+        Ok(MediaResponse { hash })
+    }
+
+    pub fn get_mempool(state: &ServiceApiState, _query: ()) -> api::Result<Vec<PendingTransaction>> {
+        let snapshot = state.snapshot();
+        let core_schema = Schema::new(&snapshot);
+        let transactions = core_schema.transactions();
+        Ok(core_schema.transactions_pool().iter()
+            .filter_map(|tx_hash| transactions.get(&tx_hash).map(|raw| (tx_hash, raw)))
+            .filter(|(_, raw)| raw.service_id() == AUCTION_SERVICE_ID)
+            .filter_map(|(tx_hash, raw)| AuctionTransactions::tx_from_raw(raw).ok().map(|content| PendingTransaction { tx_hash, content }))
+            .collect())
+    }
+
+    pub fn get_schema_stats(state: &ServiceApiState, _query: ()) -> api::Result<SchemaStats> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let mut stats = SchemaStats::default();
+        for lot_pub_key in schema.lots().keys() {
+            stats.lots += 1;
+            stats.lot_txs += schema.lot_txs(&lot_pub_key).len();
+        }
+        stats.bids = schema.bids().keys().count() as u64;
+        stats.lot_outcomes = schema.lot_outcomes().len();
+        stats.stats_snapshots = schema.stats_history().keys().count() as u64;
+        stats.media = schema.media().iter().count() as u64;
+        stats.blobs = schema.blobs().keys().count() as u64;
+        stats.webhooks = schema.webhooks().keys().count() as u64;
+        Ok(stats)
+    }
+
+    // Only node-local indexes are written, so the node keeps committing blocks meanwhile.
+    pub fn rebuild_indexes(state: &ServiceApiState, _query: ()) -> api::Result<RebuildReport> {
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        let lot_txs = rebuild_lot_txs(&mut fork);
+        blockchain.merge(fork.into_patch())?;
+        Ok(RebuildReport { lot_txs })
+    }
+
+    pub fn get_settings(state: &ServiceApiState, defaults: &LocalSettings) -> api::Result<NodeSettings> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        let stored = LocalSettings::stored(&schema);
+        let overridden = stored.is_some();
+        let settings = stored.unwrap_or_else(|| defaults.clone());
+        Ok(NodeSettings {
+            media: settings.media,
+            webhook_retry: settings.webhook_retry,
+            webhooks: webhooks::registered(&schema).len(),
+            overridden
+        })
+    }
+
+    // Takes effect at once and survives restarts; the node config is left as is.
+    pub fn post_settings(state: &ServiceApiState, query: LocalSettings, defaults: &LocalSettings) -> api::Result<NodeSettings> {
+        query.check().map_err(|reason| Error::InvalidQuery.with("reason", reason))?;
+        let raw = ::serde_json::to_string(&query).map_err(|e| api::Error::InternalError(e.into()))?;
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        ex_schema::AuctionSchema::new(&mut fork).local_settings_mut().set(raw);
+        blockchain.merge(fork.into_patch())?;
+        Self::get_settings(state, defaults)
+    }
+
+    pub fn reset_settings(state: &ServiceApiState, defaults: &LocalSettings) -> api::Result<NodeSettings> {
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        ex_schema::AuctionSchema::new(&mut fork).local_settings_mut().remove();
+        blockchain.merge(fork.into_patch())?;
+        Self::get_settings(state, defaults)
+    }
+
+    pub fn get_webhooks(state: &ServiceApiState, _query: ()) -> api::Result<Vec<WebhookEntry>> {
+        let snapshot = state.snapshot();
+        let schema = ex_schema::AuctionSchema::new(snapshot);
+        Ok(webhooks::registered(&schema).into_iter().map(|(id, webhook)| WebhookEntry { id, webhook: webhook.redacted() }).collect())
+    }
+
+    // Replays the events of committed blocks to every registered webhook, e.g. after a restart
+    // dropped the deliveries that were still queued.
+    pub fn redeliver_webhooks(state: &ServiceApiState, query: RedeliverQuery, queue: &WebhookQueue, defaults: &LocalSettings) -> api::Result<RedeliverReport> {
+        let snapshot = state.snapshot();
+        let height = Schema::new(&snapshot).height().0;
+        if query.from_height > query.to_height || query.to_height > height {
+            return Err(Error::InvalidQuery
+                .with("reason", "Heights must form a range of committed blocks")
+                .with("from_height", query.from_height)
+                .with("to_height", query.to_height)
+                .into())
+        }
+        let schema = ex_schema::AuctionSchema::new(&snapshot);
+        let policy = defaults.effective(&schema).webhook_retry;
+        let webhooks: Vec<_> = webhooks::registered(&schema)
+            .into_iter()
+            .map(|(_, webhook)| webhook)
+            .collect();
+        let mut report = RedeliverReport { events: 0, queued: 0 };
+        for height in query.from_height..=query.to_height {
+            let events = block_events(&snapshot, height);
+            report.events += events.len() as u64;
+            report.queued += queue.dispatch(&webhooks, &events, policy) as u64;
+        }
+        Ok(report)
+    }
+
+    pub fn post_webhook(state: &ServiceApiState, query: Webhook) -> api::Result<WebhookQuery> {
+        match Url::parse(&query.url) {
+            Ok(ref url) if url.scheme() == "http" || url.scheme() == "https" => (),
+            _ => return Err(Error::InvalidQuery.with("reason", "Webhook URL must be http or https").with("url", &query.url).into())
+        }
+        let id = query.id();
+        let raw = ::serde_json::to_string(&query).map_err(|e| api::Error::InternalError(e.into()))?;
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        ex_schema::AuctionSchema::new(&mut fork).webhooks_mut().put(&id, raw);
+        blockchain.merge(fork.into_patch())?;
+        Ok(WebhookQuery { id })
+    }
+
+    pub fn delete_webhook(state: &ServiceApiState, query: WebhookQuery) -> api::Result<WebhookQuery> {
+        let mut blockchain = state.blockchain().clone();
+        let mut fork = blockchain.fork();
+        {
+            let mut schema = ex_schema::AuctionSchema::new(&mut fork);
+            if schema.webhooks().get(&query.id).is_none() {
+                return Err(Error::WebhookNotFound.with("id", query.id).into())
+            }
+            schema.webhooks_mut().remove(&query.id);
+        }
+        blockchain.merge(fork.into_patch())?;
+        Ok(query)
+    }
+
+    pub fn post_graphql(state: &ServiceApiState, query: GraphQLRequest) -> api::Result<::serde_json::Value> {
+        let context = graphql::Context::new(state.snapshot());
+        let response = graphql::execute(&query, &context);
+        ::serde_json::to_value(&response).map_err(|e| api::Error::InternalError(e.into()))
+    }
+
+    pub fn get_spec(_state: &ServiceApiState, _query: ()) -> api::Result<::serde_json::Value> {
+        Ok(spec::openapi())
+    }
+
+    pub fn subscribe(request: HttpRequest, hub: &EventHub) -> FutureResponse {
+        let response = serde_urlencoded::from_str::<EventFilter>(request.query_string())
+            .map_err(|e| actix_web::Error::from(api::Error::from(Error::InvalidQuery.with("reason", e.to_string()))))
+            .and_then(|filter| ws::start(request, EventSession::new(hub.clone(), filter)));
+        Box::new(future::result(response))
+    }
+
+    pub fn wire(builder: &mut ServiceApiBuilder, context: ApiContext) {
+        let versions: Vec<VersionInfo> = API_VERSIONS.iter()
+            .map(|version| {
+                let routes = (version.routes)(&context);
+                routes.mount(builder, version);
+                routes.info(version)
+            })
+            .collect();
         builder
         .public_scope()
-        .endpoint("lot", Self::get_lot)
-        .endpoint_mut("lot", Self::post_transaction)
-        .endpoint("bid", Self::get_bid)
-        .endpoint_mut("bid", Self::post_transaction)
-        .endpoint("lot_bids", Self::get_lot_bids)
-        .endpoint_mut("close_lot", Self::post_transaction);
+        .endpoint("versions", move |_state: &ServiceApiState, _query: ()| -> api::Result<Vec<VersionInfo>> {
+            Ok(versions.clone())
+        });
+    }
+
+    fn routes_v1(context: &ApiContext) -> ApiRoutes {
+        let media_settings = context.settings.clone();
+        let get_settings = context.settings.clone();
+        let post_settings = context.settings.clone();
+        let reset_settings = context.settings.clone();
+        let redeliver_settings = context.settings.clone();
+        let events = context.events.clone();
+        let webhook_queue = context.webhooks.clone();
+        let mut routes = ApiRoutes::default();
+        routes
+        .public()
+        .tag("lots")
+        .get("lot", Self::get_lot, "Get a lot, optionally as of a block height")
+        .post("lot", Self::post_transaction::<TxCreateLot>, "Submit a lot creation transaction")
+        .get("lots", Self::get_lots, "List lots in key order, optionally of one seller; delisted lots are left out unless asked for")
+        .post("lots/batch", Self::get_lots_batch, "Look up several lots at once")
+        .get("lot_history", Self::get_lot_history, "List every recorded state of a lot")
+        .get("lot_txs", Self::get_lot_txs, "List the transactions touching a lot")
+        .post("close_lot", Self::post_transaction::<TxCloseLot>, "Submit a lot closing transaction")
+        .tag("bids")
+        .get("bid", Self::get_bid, "Get a bid, optionally as of a block height")
+        .post("bid", Self::post_transaction::<TxCreateBid>, "Submit a bid transaction")
+        .get("lot_bids", Self::get_lot_bids, "List the bids of a lot")
+        .post("bids/batch", Self::get_bids_batch, "Look up several bids at once")
+        .tag("transactions")
+        .get("tx_status", Self::get_tx_status, "Report whether an auction transaction is pending, committed or failed")
+        .post("dry_run", Self::post_dry_run, "Execute any auction transaction against committed state without broadcasting it; advisory, pool transactions are not applied")
+        .tag("stats")
+        .get("stats", Self::get_stats, "Aggregate auction statistics over a height window")
+        .tag("config")
+        .get("config", Self::get_config, "Show the service config fixed in the genesis block")
+        .get("config/pending", Self::get_pending_config, "Show the accepted config proposal waiting for its activation height, or null")
+        .get("config/proposals", Self::get_config_proposals, "List every config proposal with its votes")
+        .get("config/history", Self::get_config_history, "List every activated config, starting with the genesis one")
+        .post("config/propose", Self::post_transaction::<TxProposeConfig>, "Submit a config proposal signed by an administrator")
+        .post("config/vote", Self::post_transaction::<TxVoteConfig>, "Submit an administrator's vote for a config proposal")
+        .tag("moderation")
+        .get("lot_moderation", Self::get_lot_moderation, "Show the latest moderation decision about a lot, or null")
+        .post("suspend_lot", Self::post_transaction::<TxSuspendLot>, "Submit an administrator's suspension of a lot, or its lifting")
+        .post("delist_lot", Self::post_transaction::<TxDelistLot>, "Submit an administrator's final delisting of a lot")
+        .post("ban_member", Self::post_transaction::<TxBanMember>, "Submit an administrator's ban of a member key, or its lifting")
+        .tag("reputation")
+        .get("settlement", Self::get_settlement, "Show how the deal behind a closed lot ended, or null")
+        .post("settle_lot", Self::post_transaction::<TxSettleLot>, "Submit an administrator's report on the deal behind a closed lot")
+        .get("reputation", Self::get_reputation, "Show the reputation score and ban status of a member key")
+        .get("ratings", Self::get_ratings, "List the ratings a member key has received, with their average")
+        .post("rate", Self::post_transaction::<TxRateCounterparty>, "Submit the seller's or the winner's rating of the other party to a settled lot")
+        .tag("media")
+        .get("media", Self::get_media, "Get a media blob referenced by a lot")
+        .post("media", move |state: &ServiceApiState, query: Media| {
+            Self::post_media(state, query, &media_settings)
+        }, "Upload a media blob to this node")
+        .tag("graphql")
+        .post("graphql", Self::post_graphql, "Run a GraphQL query against a single snapshot of the auction state")
+        .tag("spec")
+        .get("spec", Self::get_spec, "This document")
+        .tag("events")
+        .stream::<EventFilter, AuctionEvent>(RequestHandler {
+            name: "events".to_owned(),
+            method: Method::GET,
+            inner: Arc::new(move |request| Self::subscribe(request, &events))
+        }, "WebSocket stream of auction events")
+        .private()
+        .tag("private")
+        .get("check_consistency", Self::check_consistency, "Check auction indexes for consistency")
+        .get("mempool", Self::get_mempool, "List auction transactions waiting in the pool")
+        .get("schema_stats", Self::get_schema_stats, "Count the entries of every auction index")
+        .post("rebuild_indexes", Self::rebuild_indexes, "Rebuild derived indexes from committed blocks")
+        .get("settings", move |state: &ServiceApiState, _query: ()| {
+            Self::get_settings(state, &get_settings)
+        }, "Show node-local settings")
+        .post("settings", move |state: &ServiceApiState, query: LocalSettings| {
+            Self::post_settings(state, query, &post_settings)
+        }, "Override node-local settings until they are reset")
+        .post("settings/reset", move |state: &ServiceApiState, _query: ()| {
+            Self::reset_settings(state, &reset_settings)
+        }, "Drop the override and go back to the settings of the node config")
+        .get("webhooks", Self::get_webhooks, "List registered webhooks")
+        .post("webhooks", Self::post_webhook, "Register a webhook")
+        .post("webhooks/delete", Self::delete_webhook, "Remove a webhook")
+        .post("webhooks/redeliver", move |state: &ServiceApiState, query: RedeliverQuery| {
+            Self::redeliver_webhooks(state, query, &webhook_queue, &redeliver_settings)
+        }, "Queue the events of a range of committed blocks for every registered webhook again");
+        routes
     }
 }
@@ -0,0 +1,252 @@
+extern crate exonum;
+extern crate ex_auction;
+extern crate clap;
+#[macro_use]
+extern crate failure;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+#[cfg(unix)]
+use std::fs::{self, Permissions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use exonum::crypto::{Hash, PublicKey};
+use exonum::encoding::serialize::FromHex;
+use exonum::helpers::config::ConfigFile;
+use serde::Serialize;
+
+use ex_auction::client::{self, AuctionClient, KeyPair, LotKeys};
+
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Wallet {
+    #[serde(default)]
+    keys: BTreeMap<String, KeyPair>,
+    #[serde(default)]
+    lots: BTreeMap<String, LotKeys>,
+    #[serde(default)]
+    bids: BTreeMap<String, KeyPair>
+}
+
+
+impl Wallet {
+    fn load(path: &str) -> Result<Self, failure::Error> {
+        if Path::new(path).exists() {
+            ConfigFile::load(path)
+        } else {
+            Ok(Wallet::default())
+        }
+    }
+
+    // The wallet holds secret keys, so only its owner may read it.
+    fn save(&self, path: &str) -> Result<(), failure::Error> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // `mode` applies only to new files, so a wallet written by an older version is tightened too.
+        #[cfg(unix)]
+        fs::set_permissions(path, Permissions::from_mode(0o600))?;
+        file.write_all(toml::Value::try_from(self)?.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    fn key(&self, name: &str) -> Result<&KeyPair, failure::Error> {
+        self.keys.get(name).ok_or_else(|| format_err!("No key named {} in the wallet", name))
+    }
+}
+
+
+fn print<T: Serialize>(value: &T) -> Result<(), failure::Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+
+fn pub_key(matches: &ArgMatches, name: &str) -> Result<PublicKey, failure::Error> {
+    let hex = matches.value_of(name).unwrap();
+    PublicKey::from_hex(hex).map_err(|_| format_err!("Malformed public key {}", hex))
+}
+
+
+fn number(matches: &ArgMatches, name: &str) -> Result<u64, failure::Error> {
+    let value = matches.value_of(name).unwrap();
+    value.parse().map_err(|_| format_err!("{} must be a non-negative integer, got {}", name, value))
+}
+
+
+fn keygen(mut wallet: Wallet, wallet_path: &str, matches: &ArgMatches) -> Result<(), failure::Error> {
+    let name = matches.value_of("NAME").unwrap();
+    if wallet.keys.contains_key(name) {
+        bail!("Key {} already exists", name);
+    }
+    let keys = KeyPair::generate();
+    println!("{}", keys.public_key.to_hex());
+    wallet.keys.insert(name.to_owned(), keys);
+    wallet.save(wallet_path)
+}
+
+
+fn create_lot(mut wallet: Wallet, wallet_path: &str, node: &AuctionClient, matches: &ArgMatches) -> Result<(), failure::Error> {
+    let seller = wallet.key(matches.value_of("seller").unwrap())?.clone();
+    let token_hash = match matches.value_of("token-hash") {
+        Some(hex) => Hash::from_hex(hex).map_err(|_| format_err!("Malformed token hash {}", hex))?,
+        None => Hash::zero()
+    };
+    let media_hashes = matches.values_of("media").into_iter().flatten()
+        .map(|hex| Hash::from_hex(hex).map_err(|_| format_err!("Malformed media hash {}", hex)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let description = matches.value_of("description").unwrap();
+    let (tx, keys) = client::create_lot(&seller, &token_hash, &media_hashes, description, number(matches, "price")?, number(matches, "min-reputation")?);
+    // Saved before submitting: without the session key the lot could never be closed.
+    wallet.lots.insert(keys.lot.public_key.to_hex(), keys);
+    wallet.save(wallet_path)?;
+    let tx_hash = node.submit_lot(&tx)?;
+    println!("Lot {} is submitted in transaction {}", tx.pub_key().to_hex(), tx_hash.to_hex());
+    Ok(())
+}
+
+
+fn create_bid(mut wallet: Wallet, wallet_path: &str, node: &AuctionClient, matches: &ArgMatches) -> Result<(), failure::Error> {
+    let bidder = wallet.key(matches.value_of("bidder").unwrap())?.clone();
+    let lot = node.lot(pub_key(matches, "lot")?, None)?;
+    let (tx, keys) = client::create_bid(&bidder, &lot, number(matches, "member-id")?, number(matches, "sum")?);
+    wallet.bids.insert(keys.public_key.to_hex(), keys);
+    wallet.save(wallet_path)?;
+    let tx_hash = node.submit_bid(&tx)?;
+    println!("Bid {} is submitted in transaction {}", tx.pub_key().to_hex(), tx_hash.to_hex());
+    Ok(())
+}
+
+
+fn close_lot(wallet: &Wallet, node: &AuctionClient, matches: &ArgMatches) -> Result<(), failure::Error> {
+    let lot_pub_key = pub_key(matches, "lot")?;
+    let keys = wallet.lots.get(&lot_pub_key.to_hex()).ok_or_else(|| format_err!("Lot {} is not in the wallet", lot_pub_key.to_hex()))?;
+    let tx_hash = node.submit_close_lot(&client::close_lot(keys))?;
+    println!("Closing of lot {} is submitted in transaction {}", lot_pub_key.to_hex(), tx_hash.to_hex());
+    Ok(())
+}
+
+
+fn rate(wallet: &Wallet, node: &AuctionClient, matches: &ArgMatches) -> Result<(), failure::Error> {
+    let party = wallet.key(matches.value_of("party").unwrap())?;
+    let lot_pub_key = pub_key(matches, "lot")?;
+    let rating = matches.value_of("rating").unwrap();
+    let rating = rating.parse().map_err(|_| format_err!("rating must be between 1 and 5, got {}", rating))?;
+    let tx = client::rate_counterparty(party, &lot_pub_key, rating, matches.value_of("review").unwrap_or(""));
+    let tx_hash = node.submit_rating(&tx)?;
+    println!("Rating for lot {} is submitted in transaction {}", lot_pub_key.to_hex(), tx_hash.to_hex());
+    Ok(())
+}
+
+
+fn show_keys(wallet: &Wallet) {
+    for (name, keys) in &wallet.keys {
+        println!("{}\t{}", name, keys.public_key.to_hex());
+    }
+    for lot in wallet.lots.keys() {
+        println!("lot\t{}", lot);
+    }
+    for bid in wallet.bids.keys() {
+        println!("bid\t{}", bid);
+    }
+}
+
+
+fn execute(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let wallet_path = matches.value_of("wallet").unwrap();
+    let wallet = Wallet::load(wallet_path)?;
+    let node = AuctionClient::new(matches.value_of("node").unwrap());
+    match matches.subcommand() {
+        ("keygen", Some(matches)) => keygen(wallet, wallet_path, matches),
+        ("keys", Some(_)) => {
+            show_keys(&wallet);
+            Ok(())
+        },
+        ("create-lot", Some(matches)) => create_lot(wallet, wallet_path, &node, matches),
+        ("bid", Some(matches)) => create_bid(wallet, wallet_path, &node, matches),
+        ("close-lot", Some(matches)) => close_lot(&wallet, &node, matches),
+        ("lot", Some(matches)) => print(&node.lot(pub_key(matches, "PUB_KEY")?, None)?),
+        ("show-bid", Some(matches)) => print(&node.bid(pub_key(matches, "PUB_KEY")?, None)?),
+        ("rate", Some(matches)) => rate(&wallet, &node, matches),
+        ("reputation", Some(matches)) => print(&node.reputation(pub_key(matches, "PUB_KEY")?)?),
+        ("ratings", Some(matches)) => print(&node.ratings(pub_key(matches, "PUB_KEY")?)?),
+        ("status", Some(matches)) => {
+            let tx_hash = matches.value_of("TX_HASH").unwrap();
+            let tx_hash = Hash::from_hex(tx_hash).map_err(|_| format_err!("Malformed transaction hash {}", tx_hash))?;
+            print(&node.tx_status(tx_hash)?)
+        },
+        _ => unreachable!()
+    }
+}
+
+
+fn main() {
+    let lot_arg = || Arg::with_name("lot").long("lot").takes_value(true).required(true).help("Public key of the lot");
+    let matches = App::new("ex_auction-cli")
+        .about("Wallet for auction sellers and bidders")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("wallet").short("w").long("wallet").takes_value(true).default_value("wallet.toml").global(true)
+            .help("Wallet file with the keys of sellers, lots and bids"))
+        .arg(Arg::with_name("node").long("node").takes_value(true).default_value("http://127.0.0.1:8000").global(true)
+            .help("Public API of the node"))
+        .subcommand(SubCommand::with_name("keygen")
+            .about("Generate a named seller or bidder keypair")
+            .arg(Arg::with_name("NAME").required(true)))
+        .subcommand(SubCommand::with_name("keys")
+            .about("List the keys in the wallet"))
+        .subcommand(SubCommand::with_name("create-lot")
+            .about("Sign and submit TxCreateLot; the lot and session keys are kept in the wallet")
+            .arg(Arg::with_name("seller").long("seller").takes_value(true).required(true).help("Name of the seller key"))
+            .arg(Arg::with_name("description").long("description").takes_value(true).required(true))
+            .arg(Arg::with_name("price").long("price").takes_value(true).required(true).help("Reserve price"))
+            .arg(Arg::with_name("token-hash").long("token-hash").takes_value(true))
+            .arg(Arg::with_name("media").long("media").takes_value(true).multiple(true).help("Hashes of uploaded media"))
+            .arg(Arg::with_name("min-reputation").long("min-reputation").takes_value(true).default_value("0")
+                .help("Lowest reputation score a bidder needs; 0 admits everyone")))
+        .subcommand(SubCommand::with_name("bid")
+            .about("Seal a bid with the lot's session key, then sign and submit TxCreateBid")
+            .arg(lot_arg())
+            .arg(Arg::with_name("bidder").long("bidder").takes_value(true).required(true).help("Name of the bidder key"))
+            .arg(Arg::with_name("sum").long("sum").takes_value(true).required(true))
+            .arg(Arg::with_name("member-id").long("member-id").takes_value(true).required(true)))
+        .subcommand(SubCommand::with_name("close-lot")
+            .about("Sign and submit TxCloseLot for a lot created from this wallet")
+            .arg(lot_arg()))
+        .subcommand(SubCommand::with_name("lot")
+            .about("Show a lot")
+            .arg(Arg::with_name("PUB_KEY").required(true)))
+        .subcommand(SubCommand::with_name("show-bid")
+            .about("Show a bid")
+            .arg(Arg::with_name("PUB_KEY").required(true)))
+        .subcommand(SubCommand::with_name("rate")
+            .about("Sign and submit TxRateCounterparty for a settled lot you sold or won")
+            .arg(lot_arg())
+            .arg(Arg::with_name("party").long("party").takes_value(true).required(true).help("Name of the seller or winner key"))
+            .arg(Arg::with_name("rating").long("rating").takes_value(true).required(true).help("From 1 to 5"))
+            .arg(Arg::with_name("review").long("review").takes_value(true)))
+        .subcommand(SubCommand::with_name("reputation")
+            .about("Show the reputation score and ban status of a member key")
+            .arg(Arg::with_name("PUB_KEY").required(true)))
+        .subcommand(SubCommand::with_name("ratings")
+            .about("Show the ratings a member key has received and their average")
+            .arg(Arg::with_name("PUB_KEY").required(true)))
+        .subcommand(SubCommand::with_name("status")
+            .about("Show whether a transaction is pending, committed or failed")
+            .arg(Arg::with_name("TX_HASH").required(true)))
+        .get_matches();
+
+    if let Err(e) = execute(&matches) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
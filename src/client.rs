@@ -0,0 +1,486 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use exonum::blockchain::Transaction;
+use exonum::crypto::{self, Hash, PublicKey, SecretKey};
+use reqwest::{self, Method, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use api::{
+    BatchQuery, BatchResponse, BidQuery, ConfigChange, ConfigProposalInfo, DryRunResponse, LotBidsQuery, LotHistoryQuery,
+    LotModerationQuery, LotQuery, LotsQuery, LotTransaction, LotTxsQuery, Media, MediaQuery, MediaResponse, MemberRatings, MemberReputation,
+    NodeSettings, PendingTransaction, RatingsQuery, RebuildReport, RedeliverQuery, RedeliverReport, ReputationQuery, SchemaStats,
+    SettlementQuery, StatsQuery, StatsReport, TransactionResponse, TransactionStatus, TxState, TxStatusQuery, WebhookEntry,
+    WebhookQuery
+};
+use config::LocalSettings;
+use consistency::ConsistencyReport;
+use errors::{Error, ErrorInfo};
+use schema::{Bid, Lot, LotModeration, LotRecord, ServiceConfig, Settlement, SettlementOutcome};
+use transactions::{
+    TxBanMember, TxCloseLot, TxCreateBid, TxCreateLot, TxDelistLot, TxProposeConfig, TxRateCounterparty, TxSettleLot, TxSuspendLot,
+    TxVoteConfig
+};
+use webhooks::{RetryPolicy, Webhook};
+use AUCTION_SERVICE_NAME;
+
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 200;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyPair {
+    pub public_key: PublicKey,
+    pub secret_key: SecretKey
+}
+
+
+impl KeyPair {
+    pub fn generate() -> Self {
+        let (public_key, secret_key) = crypto::gen_keypair();
+        KeyPair { public_key, secret_key }
+    }
+}
+
+
+// The lot key signs `TxCreateLot` and `TxCloseLot`, and the session key opens the sealed bids.
+// Losing them means the lot can never be closed, so callers should persist them before submitting.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LotKeys {
+    pub lot: KeyPair,
+    pub session: KeyPair
+}
+
+
+// Bidders whose reputation score is below a non-zero `min_reputation` are turned away.
+pub fn create_lot(seller: &KeyPair, token_hash: &Hash, media_hashes: &[Hash], description: &str, price: u64, min_reputation: u64) -> (TxCreateLot, LotKeys) {
+    let keys = LotKeys { lot: KeyPair::generate(), session: KeyPair::generate() };
+    let tx = TxCreateLot::new(
+        &keys.lot.public_key,
+        &seller.public_key,
+        &TxCreateLot::sign_lot_key(&keys.lot.public_key, &seller.secret_key),
+        &keys.session.public_key,
+        token_hash,
+        media_hashes,
+        description,
+        price,
+        min_reputation,
+        &keys.lot.secret_key
+    );
+    (tx, keys)
+}
+
+
+// The sum and member id are sealed with the lot's session key, so only the seller learns them on close.
+// The transaction is signed by the bidder, whose key bans and reputation apply to.
+pub fn create_bid(bidder: &KeyPair, lot: &Lot, member_id: u64, sum: u64) -> (TxCreateBid, KeyPair) {
+    let keys = KeyPair::generate();
+    let bid = Bid::new(&keys.public_key, lot.pub_key(), member_id, sum, false, vec![]).encrypt(lot.pub_session_key());
+    let bid_signature = TxCreateBid::sign_member_key(&bidder.public_key, &keys.secret_key);
+    let tx = TxCreateBid::new(&keys.public_key, lot.pub_key(), &bidder.public_key, &bid_signature, bid.data(), &bidder.secret_key);
+    (tx, keys)
+}
+
+
+pub fn close_lot(keys: &LotKeys) -> TxCloseLot {
+    TxCloseLot::new(&keys.lot.public_key, &keys.session.public_key, &keys.session.secret_key.to_hex(), &keys.lot.secret_key)
+}
+
+
+pub fn propose_config(admin: &KeyPair, config: &ServiceConfig, actual_from: u64) -> TxProposeConfig {
+    TxProposeConfig::new(&admin.public_key, &::serde_json::to_string(config).unwrap(), actual_from, &admin.secret_key)
+}
+
+
+pub fn vote_config(admin: &KeyPair, proposal_hash: &Hash) -> TxVoteConfig {
+    TxVoteConfig::new(&admin.public_key, proposal_hash, &admin.secret_key)
+}
+
+
+// Pass `suspended: false` to lift an earlier suspension.
+pub fn suspend_lot(admin: &KeyPair, lot_pub_key: &PublicKey, suspended: bool, reason: &str) -> TxSuspendLot {
+    TxSuspendLot::new(&admin.public_key, lot_pub_key, suspended, reason, &admin.secret_key)
+}
+
+
+pub fn delist_lot(admin: &KeyPair, lot_pub_key: &PublicKey, reason: &str) -> TxDelistLot {
+    TxDelistLot::new(&admin.public_key, lot_pub_key, reason, &admin.secret_key)
+}
+
+
+// Pass `banned: false` to lift an earlier ban.
+pub fn ban_member(admin: &KeyPair, member_pub_key: &PublicKey, banned: bool, reason: &str) -> TxBanMember {
+    TxBanMember::new(&admin.public_key, member_pub_key, banned, reason, &admin.secret_key)
+}
+
+
+pub fn settle_lot(admin: &KeyPair, lot_pub_key: &PublicKey, outcome: SettlementOutcome) -> TxSettleLot {
+    TxSettleLot::new(&admin.public_key, lot_pub_key, outcome as u8, &admin.secret_key)
+}
+
+
+// Signed by the seller or the winner of a settled lot; the other party is the one rated.
+pub fn rate_counterparty(party: &KeyPair, lot_pub_key: &PublicKey, rating: u8, review: &str) -> TxRateCounterparty {
+    TxRateCounterparty::new(&party.public_key, lot_pub_key, rating, review, &party.secret_key)
+}
+
+
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    #[fail(display = "{}", _0)]
+    Transport(#[cause] reqwest::Error),
+    // The service rejected the request with its `ErrorInfo`.
+    #[fail(display = "{}: {}", _0, _1)]
+    Api(StatusCode, ErrorInfo),
+    #[fail(display = "{}: {}", _0, _1)]
+    Http(StatusCode, String),
+    #[fail(display = "Private API address is not configured")]
+    NoPrivateApi,
+    #[fail(display = "Transaction {} is still pending", _0)]
+    Timeout(Hash)
+}
+
+
+impl ClientError {
+    pub fn info(&self) -> Option<&ErrorInfo> {
+        match *self {
+            ClientError::Api(_, ref info) => Some(info),
+            _ => None
+        }
+    }
+
+    // Transport failures and server errors may pass; anything else fails the same way again.
+    fn is_transient(&self) -> bool {
+        match *self {
+            ClientError::Transport(_) => true,
+            ClientError::Api(status, _) | ClientError::Http(status, _) => status.is_server_error(),
+            _ => false
+        }
+    }
+}
+
+
+impl From<reqwest::Error> for ClientError {
+    fn from(value: reqwest::Error) -> ClientError {
+        ClientError::Transport(value)
+    }
+}
+
+
+pub type Result<T> = ::std::result::Result<T, ClientError>;
+
+
+#[derive(Debug, Clone, Copy)]
+enum Scope {
+    Public,
+    Private
+}
+
+
+// Typed client of the service API for backend services. Requests are retried with the
+// `RetryPolicy` of webhooks, which is safe since reads are idempotent and a resubmitted
+// transaction has the same hash.
+pub struct AuctionClient {
+    public_url: String,
+    private_url: Option<String>,
+    retry_policy: RetryPolicy,
+    poll_interval: Duration,
+    client: reqwest::Client
+}
+
+
+impl fmt::Debug for AuctionClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuctionClient")
+            .field("public_url", &self.public_url)
+            .field("private_url", &self.private_url)
+            .field("retry_policy", &self.retry_policy)
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+
+fn service_url(api_url: &str) -> String {
+    format!("{}/api/services/{}/", api_url.trim_end_matches('/'), AUCTION_SERVICE_NAME)
+}
+
+
+impl AuctionClient {
+    // `public_api` is the node's public API root, such as `http://127.0.0.1:8000`.
+    pub fn new(public_api: &str) -> Self {
+        AuctionClient {
+            public_url: service_url(public_api),
+            private_url: None,
+            retry_policy: RetryPolicy::default(),
+            poll_interval: Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
+            client: reqwest::Client::new()
+        }
+    }
+
+    pub fn with_private_api(mut self, private_api: &str) -> Self {
+        self.private_url = Some(service_url(private_api));
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn url(&self, scope: Scope, endpoint: &str) -> Result<String> {
+        let base = match scope {
+            Scope::Public => &self.public_url,
+            Scope::Private => self.private_url.as_ref().ok_or(ClientError::NoPrivateApi)?
+        };
+        Ok(format!("{}{}", base, endpoint))
+    }
+
+    // Parameterless GETs send no query string at all, since `()` is not a valid one.
+    fn request<Q: Serialize, T: DeserializeOwned>(&self, method: Method, scope: Scope, endpoint: &str, query: Option<&Q>) -> Result<T> {
+        let url = self.url(scope, endpoint)?;
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.request(method.clone(), &url);
+            match (method == Method::Get, query) {
+                (true, Some(query)) => { request.query(query); },
+                (true, None) => {},
+                (false, query) => { request.json(&query); }
+            }
+            match request.send().map_err(ClientError::from).and_then(Self::read) {
+                Err(ref e) if e.is_transient() && attempt + 1 < self.retry_policy.attempts => {
+                    thread::sleep(self.retry_policy.delay(attempt));
+                    attempt += 1;
+                },
+                result => return result
+            }
+        }
+    }
+
+    fn read<T: DeserializeOwned>(mut response: reqwest::Response) -> Result<T> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json()?);
+        }
+        let body = response.text()?;
+        match ::serde_json::from_str(&body) {
+            Ok(info) => Err(ClientError::Api(status, info)),
+            Err(_) => Err(ClientError::Http(status, body))
+        }
+    }
+
+    fn get<Q: Serialize, T: DeserializeOwned>(&self, endpoint: &str, query: &Q) -> Result<T> {
+        self.request(Method::Get, Scope::Public, endpoint, Some(query))
+    }
+
+    fn post<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T> {
+        self.request(Method::Post, Scope::Public, endpoint, Some(body))
+    }
+
+    fn get_private<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        self.request::<(), T>(Method::Get, Scope::Private, endpoint, None)
+    }
+
+    fn post_private<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T> {
+        self.request(Method::Post, Scope::Private, endpoint, Some(body))
+    }
+
+    pub fn lot(&self, pub_key: PublicKey, height: Option<u64>) -> Result<Lot> {
+        self.get("v1/lot", &LotQuery { pub_key, height })
+    }
+
+    pub fn bid(&self, pub_key: PublicKey, height: Option<u64>) -> Result<Bid> {
+        self.get("v1/bid", &BidQuery { pub_key, height })
+    }
+
+    pub fn lot_bids(&self, lot_pub_key: PublicKey, height: Option<u64>) -> Result<Vec<Bid>> {
+        self.get("v1/lot_bids", &LotBidsQuery { lot_pub_key, height })
+    }
+
+    pub fn lots(&self, query: &LotsQuery) -> Result<Vec<Lot>> {
+        self.get("v1/lots", query)
+    }
+
+    pub fn lots_batch(&self, pub_keys: Vec<PublicKey>, height: Option<u64>, include_delisted: bool) -> Result<BatchResponse<Lot>> {
+        self.post("v1/lots/batch", &BatchQuery { pub_keys, height, include_delisted })
+    }
+
+    pub fn bids_batch(&self, pub_keys: Vec<PublicKey>, height: Option<u64>) -> Result<BatchResponse<Bid>> {
+        self.post("v1/bids/batch", &BatchQuery { pub_keys, height, include_delisted: false })
+    }
+
+    pub fn lot_moderation(&self, lot_pub_key: PublicKey) -> Result<Option<LotModeration>> {
+        self.get("v1/lot_moderation", &LotModerationQuery { lot_pub_key })
+    }
+
+    pub fn reputation(&self, pub_key: PublicKey) -> Result<MemberReputation> {
+        self.get("v1/reputation", &ReputationQuery { pub_key })
+    }
+
+    pub fn settlement(&self, lot_pub_key: PublicKey) -> Result<Option<Settlement>> {
+        self.get("v1/settlement", &SettlementQuery { lot_pub_key })
+    }
+
+    pub fn ratings(&self, pub_key: PublicKey) -> Result<MemberRatings> {
+        self.get("v1/ratings", &RatingsQuery { pub_key })
+    }
+
+    pub fn lot_history(&self, pub_key: PublicKey) -> Result<Vec<LotRecord>> {
+        self.get("v1/lot_history", &LotHistoryQuery { pub_key })
+    }
+
+    pub fn lot_txs(&self, lot_pub_key: PublicKey) -> Result<Vec<LotTransaction>> {
+        self.get("v1/lot_txs", &LotTxsQuery { lot_pub_key })
+    }
+
+    pub fn tx_status(&self, tx_hash: Hash) -> Result<TransactionStatus> {
+        self.get("v1/tx_status", &TxStatusQuery { tx_hash })
+    }
+
+    pub fn stats(&self, query: StatsQuery) -> Result<StatsReport> {
+        self.get("v1/stats", &query)
+    }
+
+    pub fn config(&self) -> Result<ServiceConfig> {
+        self.request::<(), _>(Method::Get, Scope::Public, "v1/config", None)
+    }
+
+    pub fn pending_config(&self) -> Result<Option<ConfigProposalInfo>> {
+        self.request::<(), _>(Method::Get, Scope::Public, "v1/config/pending", None)
+    }
+
+    pub fn config_proposals(&self) -> Result<Vec<ConfigProposalInfo>> {
+        self.request::<(), _>(Method::Get, Scope::Public, "v1/config/proposals", None)
+    }
+
+    pub fn config_history(&self) -> Result<Vec<ConfigChange>> {
+        self.request::<(), _>(Method::Get, Scope::Public, "v1/config/history", None)
+    }
+
+    pub fn media(&self, hash: Hash) -> Result<Media> {
+        self.get("v1/media", &MediaQuery { hash })
+    }
+
+    pub fn upload_media(&self, media: &Media) -> Result<Hash> {
+        self.post::<_, MediaResponse>("v1/media", media).map(|response| response.hash)
+    }
+
+    pub fn graphql(&self, query: &str, variables: Option<Value>) -> Result<Value> {
+        self.post("v1/graphql", &json!({ "query": query, "variables": variables }))
+    }
+
+    pub fn spec(&self) -> Result<Value> {
+        self.request::<(), _>(Method::Get, Scope::Public, "v1/spec", None)
+    }
+
+    pub fn submit_lot(&self, tx: &TxCreateLot) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/lot", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_bid(&self, tx: &TxCreateBid) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/bid", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_close_lot(&self, tx: &TxCloseLot) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/close_lot", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_propose_config(&self, tx: &TxProposeConfig) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/config/propose", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_vote_config(&self, tx: &TxVoteConfig) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/config/vote", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_suspend_lot(&self, tx: &TxSuspendLot) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/suspend_lot", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_delist_lot(&self, tx: &TxDelistLot) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/delist_lot", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_ban_member(&self, tx: &TxBanMember) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/ban_member", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_settle_lot(&self, tx: &TxSettleLot) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/settle_lot", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn submit_rating(&self, tx: &TxRateCounterparty) -> Result<Hash> {
+        self.post::<_, TransactionResponse>("v1/rate", tx).map(|response| response.tx_hash)
+    }
+
+    pub fn dry_run<T: Transaction + Serialize>(&self, tx: &T) -> Result<DryRunResponse> {
+        self.post("v1/dry_run", tx)
+    }
+
+    // Polls until the transaction leaves the pool. A transaction that is not known yet counts
+    // as pending, since the node accepts it into the pool asynchronously.
+    pub fn wait_for_status(&self, tx_hash: Hash, timeout: Duration) -> Result<TransactionStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.tx_status(tx_hash) {
+                Ok(ref status) if status.status == TxState::Pending => {},
+                Err(ClientError::Api(_, ref info)) if info.code == Error::TransactionNotFound.code() => {},
+                result => return result
+            }
+            if Instant::now() >= deadline {
+                return Err(ClientError::Timeout(tx_hash));
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    pub fn check_consistency(&self) -> Result<ConsistencyReport> {
+        self.get_private("v1/check_consistency")
+    }
+
+    pub fn mempool(&self) -> Result<Vec<PendingTransaction>> {
+        self.get_private("v1/mempool")
+    }
+
+    pub fn schema_stats(&self) -> Result<SchemaStats> {
+        self.get_private("v1/schema_stats")
+    }
+
+    pub fn rebuild_indexes(&self) -> Result<RebuildReport> {
+        self.post_private("v1/rebuild_indexes", &())
+    }
+
+    pub fn settings(&self) -> Result<NodeSettings> {
+        self.get_private("v1/settings")
+    }
+
+    pub fn set_settings(&self, settings: &LocalSettings) -> Result<NodeSettings> {
+        self.post_private("v1/settings", settings)
+    }
+
+    pub fn reset_settings(&self) -> Result<NodeSettings> {
+        self.post_private("v1/settings/reset", &())
+    }
+
+    pub fn webhooks(&self) -> Result<Vec<WebhookEntry>> {
+        self.get_private("v1/webhooks")
+    }
+
+    pub fn add_webhook(&self, webhook: &Webhook) -> Result<Hash> {
+        self.post_private::<_, WebhookQuery>("v1/webhooks", webhook).map(|response| response.id)
+    }
+
+    pub fn delete_webhook(&self, id: Hash) -> Result<Hash> {
+        self.post_private::<_, WebhookQuery>("v1/webhooks/delete", &WebhookQuery { id }).map(|response| response.id)
+    }
+
+    pub fn redeliver_webhooks(&self, from_height: u64, to_height: u64) -> Result<RedeliverReport> {
+        self.post_private("v1/webhooks/redeliver", &RedeliverQuery { from_height, to_height })
+    }
+}
@@ -0,0 +1,203 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use exonum::blockchain::{GenesisConfig, ValidatorKeys};
+use exonum::crypto::{self, PublicKey, SecretKey};
+use exonum::node::{ConnectInfo, ConnectListConfig, NodeApiConfig, NodeConfig};
+use exonum::storage::{Database, DbOptions, MemoryDB, RocksDB, Snapshot};
+use failure;
+use serde_json;
+use toml;
+use media::MediaConfig;
+use schema::{AuctionSchema, ServiceConfig};
+use webhooks::RetryPolicy;
+use {AuctionService, AUCTION_SERVICE_NAME};
+
+
+pub const DEFAULT_PEER_ADDRESS: &str = "0.0.0.0:2000";
+pub const DEFAULT_PUBLIC_API_ADDRESS: &str = "0.0.0.0:8000";
+pub const DEFAULT_PRIVATE_API_ADDRESS: &str = "127.0.0.1:8010";
+pub const DEFAULT_TESTNET_PEER_PORT: u16 = 2000;
+pub const DEFAULT_TESTNET_API_PORT: u16 = 8000;
+
+
+// Service settings, kept in `[services_configs.ex_auction]` of the node config. `media` and
+// `webhook_retry` are node-local, while `genesis` must be the same on every validator.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuctionConfig {
+    pub media: MediaConfig,
+    pub webhook_retry: RetryPolicy,
+    pub genesis: ServiceConfig
+}
+
+
+// The node-local part of `AuctionConfig`. An override stored through `v1/settings` wins over
+// the node config until it is reset.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct LocalSettings {
+    pub media: MediaConfig,
+    pub webhook_retry: RetryPolicy
+}
+
+
+impl LocalSettings {
+    pub fn check(&self) -> Result<(), String> {
+        if self.webhook_retry.attempts == 0 {
+            return Err("Webhook retry needs at least one attempt".to_owned())
+        }
+        Ok(())
+    }
+
+    pub fn stored<T: AsRef<dyn Snapshot>>(schema: &AuctionSchema<T>) -> Option<Self> {
+        schema.local_settings().and_then(|raw| serde_json::from_str(&raw).ok())
+    }
+
+    // The stored override, or `self` when there is none.
+    pub fn effective<T: AsRef<dyn Snapshot>>(&self, schema: &AuctionSchema<T>) -> Self {
+        Self::stored(schema).unwrap_or_else(|| self.clone())
+    }
+}
+
+
+impl AuctionConfig {
+    pub fn from_node_config(config: &NodeConfig) -> Result<Self, failure::Error> {
+        match config.services_configs.get(AUCTION_SERVICE_NAME) {
+            Some(value) => Ok(value.clone().try_into()?),
+            None => Ok(AuctionConfig::default())
+        }
+    }
+
+    pub fn service(&self) -> AuctionService {
+        AuctionService::new(self.media.clone())
+            .with_retry_policy(self.webhook_retry)
+            .with_config(self.genesis.clone())
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NodeKeys {
+    pub consensus_public_key: PublicKey,
+    pub consensus_secret_key: SecretKey,
+    pub service_public_key: PublicKey,
+    pub service_secret_key: SecretKey
+}
+
+
+impl NodeKeys {
+    pub fn generate() -> Self {
+        let (consensus_public_key, consensus_secret_key) = crypto::gen_keypair();
+        let (service_public_key, service_secret_key) = crypto::gen_keypair();
+        NodeKeys { consensus_public_key, consensus_secret_key, service_public_key, service_secret_key }
+    }
+
+    pub fn validator_keys(&self) -> ValidatorKeys {
+        ValidatorKeys { consensus_key: self.consensus_public_key, service_key: self.service_public_key }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct NodeTemplate {
+    pub keys: NodeKeys,
+    // Genesis validators; empty means this node validates alone.
+    pub validators: Vec<ValidatorKeys>,
+    pub peers: Vec<ConnectInfo>,
+    pub peer_address: SocketAddr,
+    pub public_api_address: SocketAddr,
+    pub private_api_address: SocketAddr,
+    pub auction: AuctionConfig
+}
+
+
+impl NodeTemplate {
+    pub fn new(keys: NodeKeys) -> Self {
+        NodeTemplate {
+            keys,
+            validators: Vec::new(),
+            peers: Vec::new(),
+            peer_address: DEFAULT_PEER_ADDRESS.parse().unwrap(),
+            public_api_address: DEFAULT_PUBLIC_API_ADDRESS.parse().unwrap(),
+            private_api_address: DEFAULT_PRIVATE_API_ADDRESS.parse().unwrap(),
+            auction: AuctionConfig::default()
+        }
+    }
+
+    pub fn build(self) -> Result<NodeConfig, failure::Error> {
+        let validators = if self.validators.is_empty() { vec![self.keys.validator_keys()] } else { self.validators };
+        let services_configs = vec![(AUCTION_SERVICE_NAME.to_owned(), toml::Value::try_from(&self.auction)?)]
+            .into_iter()
+            .collect();
+        Ok(NodeConfig {
+            listen_address: self.peer_address,
+            external_address: Some(self.peer_address),
+            service_public_key: self.keys.service_public_key,
+            service_secret_key: self.keys.service_secret_key,
+            consensus_public_key: self.keys.consensus_public_key,
+            consensus_secret_key: self.keys.consensus_secret_key,
+            genesis: GenesisConfig::new(validators.into_iter()),
+            network: Default::default(),
+            connect_list: ConnectListConfig { peers: self.peers },
+            api: NodeApiConfig {
+                public_api_address: Some(self.public_api_address),
+                private_api_address: Some(self.private_api_address),
+                ..Default::default()
+            },
+            mempool: Default::default(),
+            services_configs,
+            database: Default::default()
+        })
+    }
+}
+
+
+// Validator `i` of a local testnet listens to peers on `peer_port + i`, serves the public API
+// on `api_port + i` and the private one on `api_port + count + i`.
+pub fn testnet(count: u16, peer_port: u16, api_port: u16) -> Result<Vec<NodeConfig>, failure::Error> {
+    let keys: Vec<_> = (0..count).map(|_| NodeKeys::generate()).collect();
+    let address = |port: u16| SocketAddr::from(([127, 0, 0, 1], port));
+    let validators: Vec<_> = keys.iter().map(NodeKeys::validator_keys).collect();
+    keys.iter().enumerate().map(|(i, node_keys)| {
+        let i = i as u16;
+        let mut template = NodeTemplate::new(node_keys.clone());
+        template.validators = validators.clone();
+        template.peers = keys.iter().enumerate()
+            .filter(|&(j, _)| j as u16 != i)
+            .map(|(j, peer)| ConnectInfo { address: address(peer_port + j as u16), public_key: peer.consensus_public_key })
+            .collect();
+        template.peer_address = address(peer_port + i);
+        template.public_api_address = address(api_port + i);
+        template.private_api_address = address(api_port + count + i);
+        template.build()
+    }).collect()
+}
+
+
+pub fn testnet_node_config(dir: &Path, validator: u16) -> PathBuf {
+    dir.join(validator.to_string()).join("node.toml")
+}
+
+
+pub fn testnet_db_path(dir: &Path, validator: u16) -> PathBuf {
+    dir.join(validator.to_string()).join("db")
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Storage {
+    // Everything is lost on restart; meant for demos.
+    Memory,
+    RocksDb(PathBuf)
+}
+
+
+impl Storage {
+    pub fn open(&self, options: &DbOptions) -> Result<Arc<dyn Database>, failure::Error> {
+        Ok(match *self {
+            Storage::Memory => Arc::new(MemoryDB::new()),
+            Storage::RocksDb(ref path) => Arc::new(RocksDB::open(path, options)?)
+        })
+    }
+}
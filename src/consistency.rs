@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+use exonum::{crypto::PublicKey, storage::Snapshot};
+use schema::{AuctionSchema, Bid};
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Inconsistency {
+    DanglingBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    MisplacedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    OrphanedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    UnlistedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    OrphanedLot { lot_pub_key: PublicKey }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    pub lots_checked: u64,
+    pub bids_checked: u64,
+    pub inconsistencies: Vec<Inconsistency>
+}
+
+
+impl<T: AsRef<dyn Snapshot>> AuctionSchema<T> {
+    pub fn lot_bids_checked(&self, lot_pub_key: &PublicKey) -> Result<Vec<Bid>, Vec<Inconsistency>> {
+        let mut bids = Vec::new();
+        let mut inconsistencies = Vec::new();
+        for bid_pub_key in self.lot_bids(lot_pub_key).iter() {
+            match self.bid(&bid_pub_key) {
+                Some(ref bid) if bid.lot_pub_key() != lot_pub_key => {
+                    inconsistencies.push(Inconsistency::MisplacedBid { lot_pub_key: *lot_pub_key, bid_pub_key })
+                },
+                Some(bid) => bids.push(bid),
+                None => inconsistencies.push(Inconsistency::DanglingBid { lot_pub_key: *lot_pub_key, bid_pub_key })
+            }
+        }
+        if inconsistencies.is_empty() {
+            Ok(bids)
+        } else {
+            Err(inconsistencies)
+        }
+    }
+
+    pub fn check_consistency(&self) -> ConsistencyReport {
+        let mut report = ConsistencyReport { lots_checked: 0, bids_checked: 0, inconsistencies: Vec::new() };
+        let mut listed_bids = HashSet::new();
+        for (lot_pub_key, _) in self.lots().iter() {
+            report.lots_checked += 1;
+            if self.lot_history(&lot_pub_key).is_empty() {
+                report.inconsistencies.push(Inconsistency::OrphanedLot { lot_pub_key });
+            }
+            listed_bids.extend(self.lot_bids(&lot_pub_key).iter());
+            if let Err(inconsistencies) = self.lot_bids_checked(&lot_pub_key) {
+                report.inconsistencies.extend(inconsistencies);
+            }
+        }
+        for (bid_pub_key, bid) in self.bids().iter() {
+            report.bids_checked += 1;
+            let lot_pub_key = *bid.lot_pub_key();
+            if self.lot(&lot_pub_key).is_none() {
+                report.inconsistencies.push(Inconsistency::OrphanedBid { lot_pub_key, bid_pub_key });
+            } else if !listed_bids.contains(&bid_pub_key) {
+                report.inconsistencies.push(Inconsistency::UnlistedBid { lot_pub_key, bid_pub_key });
+            }
+        }
+        report
+    }
+}
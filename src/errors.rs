@@ -1,19 +1,182 @@
 #![allow(bare_trait_objects)]
-use exonum::blockchain::ExecutionError;
+use std::collections::BTreeMap;
+use std::fmt;
+use exonum::api;
+use exonum::blockchain::{ExecutionError, TransactionError, TransactionErrorType};
+use serde::Serialize;
+use serde_json::{self, Value};
 
 
-#[derive(Debug, Fail)]
+// Codes are part of the public API: never renumber, only append.
+#[derive(Debug, Fail, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum Error {
     #[fail(display = "Lot already exists")]
     LotAlreadyExists = 0,
     #[fail(display = "Lot is not found")]
     LotNotFound = 1,
+    #[fail(display = "Lot is closed")]
+    LotClosed = 2,
+    #[fail(display = "Bid already exists")]
+    BidAlreadyExists = 3,
+    #[fail(display = "Bid is not found")]
+    BidNotFound = 4,
+    #[fail(display = "Session key does not match the lot")]
+    SessionKeyMismatch = 5,
+    #[fail(display = "Bid ciphertext is malformed")]
+    BadCiphertext = 6,
+    #[fail(display = "Media is not found")]
+    MediaNotFound = 7,
+    #[fail(display = "Media is rejected")]
+    MediaRejected = 8,
+    #[fail(display = "Query is invalid")]
+    InvalidQuery = 9,
+    #[fail(display = "Auction state is inconsistent")]
+    InconsistentState = 10,
+    #[fail(display = "Webhook is not found")]
+    WebhookNotFound = 11,
+    #[fail(display = "Transaction signature is invalid")]
+    InvalidSignature = 12,
+    #[fail(display = "Transaction execution panicked")]
+    ExecutionPanic = 13,
+    #[fail(display = "Transaction is not found")]
+    TransactionNotFound = 14,
+    #[fail(display = "Lot description is too long")]
+    DescriptionTooLong = 15,
+    #[fail(display = "Signer is not an administrator")]
+    NotAdmin = 16,
+    #[fail(display = "Service config is invalid")]
+    InvalidConfig = 17,
+    #[fail(display = "Config proposal is not found")]
+    ProposalNotFound = 18,
+    #[fail(display = "Administrator has already voted for the proposal")]
+    AlreadyVoted = 19,
+    #[fail(display = "Config proposal is closed")]
+    ProposalClosed = 20,
+    // 21 was a conflict with the pending config; an accepted proposal now supersedes it.
+    #[fail(display = "Lot is suspended")]
+    LotSuspended = 22,
+    #[fail(display = "Lot is delisted")]
+    LotDelisted = 23,
+    #[fail(display = "Member is banned")]
+    MemberBanned = 24,
+    #[fail(display = "Member reputation is below the lot minimum")]
+    ReputationTooLow = 25,
+    #[fail(display = "Lot has no winner to settle with")]
+    LotNotSold = 26,
+    #[fail(display = "Lot is already settled")]
+    LotAlreadySettled = 27,
+    #[fail(display = "Settlement outcome is unknown")]
+    InvalidOutcome = 28,
+    #[fail(display = "Lot is not settled yet")]
+    LotNotSettled = 29,
+    #[fail(display = "Signer is not a party to the lot")]
+    NotLotParty = 30,
+    #[fail(display = "Party has already rated the lot")]
+    AlreadyRated = 31,
+    #[fail(display = "Rating is invalid")]
+    InvalidRating = 32,
+    #[fail(display = "Highest bid is below the lot price")]
+    BidTooLow = 33,
+    #[fail(display = "Party cannot rate itself")]
+    SelfRating = 34,
+}
+
+
+impl Error {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn with<V: Serialize>(self, key: &str, value: V) -> ErrorInfo {
+        ErrorInfo::from(self).with(key, value)
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ErrorInfo {
+    pub code: u8,
+    pub message: String,
+    pub context: BTreeMap<String, Value>
+}
+
+
+impl ErrorInfo {
+    pub fn with<V: Serialize>(mut self, key: &str, value: V) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.context.insert(key.to_owned(), value);
+        self
+    }
+
+    // Reverses `From<ErrorInfo> for ExecutionError`; foreign descriptions become the message.
+    pub fn from_description(code: u8, description: Option<&str>) -> Self {
+        description.and_then(|description| serde_json::from_str(description).ok())
+            .unwrap_or_else(|| ErrorInfo {
+                code,
+                message: description.unwrap_or_default().to_owned(),
+                context: BTreeMap::new()
+            })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!("\"{}\"", self.message))
+    }
+}
+
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_json())
+    }
+}
+
+
+impl From<Error> for ErrorInfo {
+    fn from(value: Error) -> ErrorInfo {
+        ErrorInfo { code: value.code(), message: value.to_string(), context: BTreeMap::new() }
+    }
+}
+
+impl<'a> From<&'a TransactionError> for ErrorInfo {
+    fn from(value: &'a TransactionError) -> ErrorInfo {
+        match value.error_type() {
+            TransactionErrorType::Code(code) => ErrorInfo::from_description(code, value.description()),
+            TransactionErrorType::Panic => Error::ExecutionPanic.with("description", value.description())
+        }
+    }
+}
+
+impl From<ErrorInfo> for ExecutionError {
+    fn from(value: ErrorInfo) -> ExecutionError {
+        ExecutionError::with_description(value.code, value.to_json())
+    }
 }
 
 impl From<Error> for ExecutionError {
     fn from(value: Error) -> ExecutionError {
-        let description = format!("{}", value);
-        ExecutionError::with_description(value as u8, description)
+        ErrorInfo::from(value).into()
+    }
+}
+
+impl From<ErrorInfo> for api::Error {
+    fn from(value: ErrorInfo) -> api::Error {
+        let body = value.to_json();
+        match value.code {
+            code if code == Error::LotNotFound.code()
+                || code == Error::BidNotFound.code()
+                || code == Error::MediaNotFound.code()
+                || code == Error::WebhookNotFound.code()
+                || code == Error::TransactionNotFound.code()
+                || code == Error::ProposalNotFound.code() => api::Error::NotFound(body),
+            code if code == Error::InconsistentState.code() => api::Error::InternalError(body.into()),
+            _ => api::Error::BadRequest(body)
+        }
     }
-}
\ No newline at end of file
+}
+
+impl From<Error> for api::Error {
+    fn from(value: Error) -> api::Error {
+        ErrorInfo::from(value).into()
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler, Syn};
+use actix_web::ws;
+use exonum::api::ServiceApiState;
+use exonum::blockchain::{Schema, TransactionSet};
+use exonum::crypto::{Hash, PublicKey};
+use exonum::helpers::Height;
+use exonum::storage::Snapshot;
+use serde_json;
+use schema::AuctionSchema;
+use transactions::AuctionTransactions;
+
+use AUCTION_SERVICE_ID;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuctionEvent {
+    LotCreated { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey },
+    BidPlaced { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey, bid_pub_key: PublicKey },
+    LotClosed {
+        height: u64,
+        tx_hash: Hash,
+        lot_pub_key: PublicKey,
+        seller_pub_key: PublicKey,
+        price: u64,
+        winner_member_id: u64,
+        bids: u64
+    },
+    LotSettled {
+        height: u64,
+        tx_hash: Hash,
+        lot_pub_key: PublicKey,
+        seller_pub_key: PublicKey,
+        winner: PublicKey,
+        outcome: u8
+    }
+}
+
+
+impl AuctionEvent {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            AuctionEvent::LotCreated { .. } => "lot_created",
+            AuctionEvent::BidPlaced { .. } => "bid_placed",
+            AuctionEvent::LotClosed { .. } => "lot_closed",
+            AuctionEvent::LotSettled { .. } => "lot_settled",
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        match *self {
+            AuctionEvent::LotCreated { height, .. } => height,
+            AuctionEvent::BidPlaced { height, .. } => height,
+            AuctionEvent::LotClosed { height, .. } => height,
+            AuctionEvent::LotSettled { height, .. } => height,
+        }
+    }
+
+    pub fn lot_pub_key(&self) -> &PublicKey {
+        match *self {
+            AuctionEvent::LotCreated { ref lot_pub_key, .. } => lot_pub_key,
+            AuctionEvent::BidPlaced { ref lot_pub_key, .. } => lot_pub_key,
+            AuctionEvent::LotClosed { ref lot_pub_key, .. } => lot_pub_key,
+            AuctionEvent::LotSettled { ref lot_pub_key, .. } => lot_pub_key,
+        }
+    }
+
+    pub fn seller_pub_key(&self) -> &PublicKey {
+        match *self {
+            AuctionEvent::LotCreated { ref seller_pub_key, .. } => seller_pub_key,
+            AuctionEvent::BidPlaced { ref seller_pub_key, .. } => seller_pub_key,
+            AuctionEvent::LotClosed { ref seller_pub_key, .. } => seller_pub_key,
+            AuctionEvent::LotSettled { ref seller_pub_key, .. } => seller_pub_key,
+        }
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct EventFilter {
+    pub lot_pub_key: Option<PublicKey>,
+    pub seller_pub_key: Option<PublicKey>
+}
+
+
+impl EventFilter {
+    pub fn matches(&self, event: &AuctionEvent) -> bool {
+        self.lot_pub_key.iter().all(|key| key == event.lot_pub_key())
+            && self.seller_pub_key.iter().all(|key| key == event.seller_pub_key())
+    }
+}
+
+
+// Only successful transactions produce events; the lot is read as of `height`
+// so a lot created and closed within one block still reports both states.
+pub fn block_events<T: AsRef<dyn Snapshot>>(snapshot: T, height: u64) -> Vec<AuctionEvent> {
+    let core_schema = Schema::new(snapshot.as_ref());
+    let schema = AuctionSchema::new(snapshot.as_ref());
+    let mut events = Vec::new();
+    for tx_hash in core_schema.block_transactions(Height(height)).iter() {
+        let raw = match core_schema.transactions().get(&tx_hash) {
+            Some(ref raw) if raw.service_id() == AUCTION_SERVICE_ID => raw.clone(),
+            _ => continue
+        };
+        match core_schema.transaction_results().get(&tx_hash) {
+            Some(Ok(())) => (),
+            _ => continue
+        }
+        let tx = match AuctionTransactions::tx_from_raw(raw) {
+            Ok(tx) => tx,
+            Err(_) => continue
+        };
+        let lot = match tx.lot_pub_key().and_then(|lot_pub_key| schema.lot_at(lot_pub_key, height)) {
+            Some(lot) => lot,
+            None => continue
+        };
+        let lot_pub_key = *lot.pub_key();
+        let seller_pub_key = *lot.seller_pub_key();
+        events.push(match tx {
+            AuctionTransactions::TxCreateLot(_) => AuctionEvent::LotCreated { height, tx_hash, lot_pub_key, seller_pub_key },
+            AuctionTransactions::TxCreateBid(ref tx) => AuctionEvent::BidPlaced {
+                height, tx_hash, lot_pub_key, seller_pub_key, bid_pub_key: *tx.pub_key()
+            },
+            AuctionTransactions::TxCloseLot(_) => AuctionEvent::LotClosed {
+                height,
+                tx_hash,
+                lot_pub_key,
+                seller_pub_key,
+                price: lot.price(),
+                winner_member_id: lot.winner_member_id(),
+                bids: schema.lot_bids(&lot_pub_key).len()
+            },
+            // A lot is settled at most once, so the current settlement is the one this transaction made.
+            AuctionTransactions::TxSettleLot(ref tx) => match schema.settlements().get(&lot_pub_key) {
+                Some(settlement) => AuctionEvent::LotSettled {
+                    height, tx_hash, lot_pub_key, seller_pub_key, winner: *settlement.winner(), outcome: tx.outcome()
+                },
+                None => continue
+            },
+            AuctionTransactions::TxProposeConfig(_) | AuctionTransactions::TxVoteConfig(_)
+                | AuctionTransactions::TxSuspendLot(_) | AuctionTransactions::TxDelistLot(_)
+                | AuctionTransactions::TxBanMember(_) | AuctionTransactions::TxRateCounterparty(_) => continue
+        });
+    }
+    events
+}
+
+
+pub trait EventSink: Send {
+    // Returns `false` once the subscriber is gone, so the hub can forget it.
+    fn deliver(&self, event: &AuctionEvent) -> bool;
+}
+
+
+impl EventSink for mpsc::Sender<AuctionEvent> {
+    fn deliver(&self, event: &AuctionEvent) -> bool {
+        self.send(event.clone()).is_ok()
+    }
+}
+
+
+type Subscriber = (EventFilter, Box<dyn EventSink>);
+
+
+#[derive(Clone, Default)]
+pub struct EventHub {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>
+}
+
+
+impl fmt::Debug for EventHub {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventHub").field("subscribers", &self.len()).finish()
+    }
+}
+
+
+impl EventHub {
+    pub fn subscribe(&self, filter: EventFilter, sink: Box<dyn EventSink>) {
+        self.subscribers.lock().unwrap().push((filter, sink));
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn publish(&self, events: &[AuctionEvent]) {
+        self.subscribers.lock().unwrap().retain(|(filter, sink)| {
+            events.iter().filter(|event| filter.matches(event)).all(|event| sink.deliver(event))
+        });
+    }
+}
+
+
+pub struct EventSession {
+    hub: EventHub,
+    filter: EventFilter
+}
+
+
+impl EventSession {
+    pub fn new(hub: EventHub, filter: EventFilter) -> Self {
+        EventSession { hub, filter }
+    }
+}
+
+
+struct EventMessage(AuctionEvent);
+
+impl Message for EventMessage {
+    type Result = ();
+}
+
+
+impl Actor for EventSession {
+    type Context = ws::WebsocketContext<Self, ServiceApiState>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let address: Addr<Syn, Self> = ctx.address();
+        self.hub.subscribe(self.filter, Box::new(address));
+    }
+}
+
+
+impl Handler<EventMessage> for EventSession {
+    type Result = ();
+
+    fn handle(&mut self, message: EventMessage, ctx: &mut Self::Context) {
+        if let Ok(text) = serde_json::to_string(&message.0) {
+            ctx.text(text);
+        }
+    }
+}
+
+
+impl StreamHandler<ws::Message, ws::ProtocolError> for EventSession {
+    fn handle(&mut self, message: ws::Message, ctx: &mut Self::Context) {
+        match message {
+            ws::Message::Ping(message) => ctx.pong(&message),
+            ws::Message::Close(_) => ctx.stop(),
+            _ => ()
+        }
+    }
+}
+
+
+impl EventSink for Addr<Syn, EventSession> {
+    fn deliver(&self, event: &AuctionEvent) -> bool {
+        if !self.connected() {
+            return false
+        }
+        self.do_send(EventMessage(event.clone()));
+        true
+    }
+}
@@ -0,0 +1,563 @@
+use exonum::crypto::PublicKey;
+use exonum::encoding::serialize::FromHex;
+use exonum::storage::Snapshot;
+use juniper::{self, EmptyMutation, FieldError, FieldResult, RootNode};
+use juniper::http::{GraphQLRequest, GraphQLResponse};
+use juniper::parser::{Lexer, Token};
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+use schema::{AuctionSchema, Lot, Bid};
+use errors::Error;
+
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+pub const MAX_QUERY_DEPTH: usize = 10;
+pub const MAX_QUERY_COMPLEXITY: usize = 5_000;
+
+
+// Every field of one request resolves against the same snapshot.
+pub struct Context {
+    snapshot: Box<dyn Snapshot>
+}
+
+
+impl Context {
+    pub fn new(snapshot: Box<dyn Snapshot>) -> Self {
+        Context { snapshot }
+    }
+
+    fn schema(&self) -> AuctionSchema<&dyn Snapshot> {
+        AuctionSchema::new(self.snapshot.as_ref())
+    }
+}
+
+
+impl juniper::Context for Context {}
+
+
+pub type AuctionGraph = RootNode<'static, Query, EmptyMutation<Context>>;
+
+
+pub fn graph() -> AuctionGraph {
+    AuctionGraph::new(Query, EmptyMutation::new())
+}
+
+
+pub fn execute<'a>(request: &'a GraphQLRequest, context: &Context) -> GraphQLResponse<'a> {
+    match check_limits(request) {
+        Ok(()) => request.execute(&graph(), context),
+        Err(e) => GraphQLResponse::error(e)
+    }
+}
+
+
+// `GraphQLRequest` keeps its fields private, so they are read back from its JSON form.
+fn check_limits(request: &GraphQLRequest) -> FieldResult<()> {
+    let request = serde_json::to_value(request).unwrap_or(Value::Null);
+    let tokens: Result<Vec<_>, _> = Lexer::new(request["query"].as_str().unwrap_or("")).map(|token| token.map(|token| token.item)).collect();
+    let document = match tokens {
+        Ok(tokens) => Document::parse(tokens),
+        Err(_) => None
+    };
+    // Malformed queries are left for the executor to report.
+    let (operations, fragments) = match document {
+        Some(document) => document,
+        None => return Ok(())
+    };
+    let measure = Measure { fragments: &fragments, variables: &request["variables"] };
+    for operation in &operations {
+        measure.selections(operation, DEFAULT_PAGE_SIZE, 0, &mut Vec::new())?;
+    }
+    Ok(())
+}
+
+
+type Fragments<'a> = HashMap<&'a str, Vec<Selection<'a>>>;
+
+
+enum Selection<'a> {
+    Field { name: &'a str, first: Option<Token<'a>>, children: Vec<Selection<'a>> },
+    Spread(&'a str),
+    Inline(Vec<Selection<'a>>)
+}
+
+
+// Just enough of the GraphQL grammar to see the shape of a query before it is executed.
+struct Document<'a> {
+    tokens: Peekable<IntoIter<Token<'a>>>
+}
+
+
+impl<'a> Document<'a> {
+    fn parse(tokens: Vec<Token<'a>>) -> Option<(Vec<Vec<Selection<'a>>>, Fragments<'a>)> {
+        let mut document = Document { tokens: tokens.into_iter().peekable() };
+        let mut operations = Vec::new();
+        let mut fragments = HashMap::new();
+        loop {
+            match document.peek() {
+                Some(Token::EndOfFile) | None => return Some((operations, fragments)),
+                Some(Token::CurlyOpen) => operations.push(document.selection_set()?),
+                Some(Token::Name("fragment")) => {
+                    document.next();
+                    let name = document.name()?;
+                    document.name()?;
+                    document.name()?;
+                    document.directives()?;
+                    fragments.insert(name, document.selection_set()?);
+                }
+                Some(Token::Name(_)) => {
+                    document.next();
+                    if let Some(Token::Name(_)) = document.peek() {
+                        document.next();
+                    }
+                    if let Some(Token::ParenOpen) = document.peek() {
+                        document.arguments()?;
+                    }
+                    document.directives()?;
+                    operations.push(document.selection_set()?);
+                }
+                _ => return None
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        self.tokens.peek()
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        self.tokens.next()
+    }
+
+    fn name(&mut self) -> Option<&'a str> {
+        match self.next() {
+            Some(Token::Name(name)) => Some(name),
+            _ => None
+        }
+    }
+
+    fn selection_set(&mut self) -> Option<Vec<Selection<'a>>> {
+        if self.next() != Some(Token::CurlyOpen) {
+            return None
+        }
+        let mut selections = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::CurlyClose) => {
+                    self.next();
+                    return Some(selections)
+                }
+                Some(Token::Ellipsis) => {
+                    self.next();
+                    match self.peek() {
+                        Some(Token::Name("on")) => {
+                            self.next();
+                            self.name()?;
+                            self.directives()?;
+                            selections.push(Selection::Inline(self.selection_set()?));
+                        }
+                        Some(Token::Name(_)) => {
+                            selections.push(Selection::Spread(self.name()?));
+                            self.directives()?;
+                        }
+                        _ => {
+                            self.directives()?;
+                            selections.push(Selection::Inline(self.selection_set()?));
+                        }
+                    }
+                }
+                Some(Token::Name(_)) => {
+                    let mut name = self.name()?;
+                    if let Some(Token::Colon) = self.peek() {
+                        self.next();
+                        name = self.name()?;
+                    }
+                    let mut first = None;
+                    if let Some(Token::ParenOpen) = self.peek() {
+                        first = self.arguments()?;
+                    }
+                    self.directives()?;
+                    let children = match self.peek() {
+                        Some(Token::CurlyOpen) => self.selection_set()?,
+                        _ => Vec::new()
+                    };
+                    selections.push(Selection::Field { name, first, children });
+                }
+                _ => return None
+            }
+        }
+    }
+
+    // Skips the arguments, keeping the value of `first`.
+    fn arguments(&mut self) -> Option<Option<Token<'a>>> {
+        let mut first = None;
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::ParenOpen | Token::BracketOpen | Token::CurlyOpen => depth += 1,
+                Token::ParenClose | Token::BracketClose | Token::CurlyClose => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(first)
+                    }
+                }
+                Token::Name("first") if depth == 1 && self.peek() == Some(&Token::Colon) => {
+                    self.next();
+                    first = match self.next()? {
+                        Token::Dollar => Some(Token::Name(self.name()?)),
+                        value => Some(value)
+                    };
+                }
+                Token::EndOfFile => return None,
+                _ => {}
+            }
+        }
+    }
+
+    fn directives(&mut self) -> Option<()> {
+        while let Some(Token::At) = self.peek() {
+            self.next();
+            self.name()?;
+            if let Some(Token::ParenOpen) = self.peek() {
+                self.arguments()?;
+            }
+        }
+        Some(())
+    }
+}
+
+
+// A field costs one plus what it selects; the selection of `edges` is paid once per item of the page
+// and `totalCount` costs as much as a full page.
+struct Measure<'a> {
+    fragments: &'a Fragments<'a>,
+    variables: &'a Value
+}
+
+
+impl<'a> Measure<'a> {
+    fn selections(&self, selections: &'a [Selection<'a>], page: usize, depth: usize, spreads: &mut Vec<&'a str>) -> FieldResult<usize> {
+        let mut complexity = 0;
+        for selection in selections {
+            complexity += match *selection {
+                Selection::Field { name, ref first, ref children } => self.field(name, first, children, page, depth + 1, spreads)?,
+                Selection::Inline(ref selections) => self.selections(selections, page, depth, spreads)?,
+                // Cycles are left for the validator to reject.
+                Selection::Spread(name) if spreads.contains(&name) => 0,
+                Selection::Spread(name) => match self.fragments.get(name) {
+                    Some(selections) => {
+                        spreads.push(name);
+                        let complexity = self.selections(selections, page, depth, spreads);
+                        spreads.pop();
+                        complexity?
+                    }
+                    None => 0
+                }
+            };
+            if complexity > MAX_QUERY_COMPLEXITY {
+                return Err(invalid_query(
+                    Error::InvalidQuery.with("reason", "Query is too complex").with("max_complexity", MAX_QUERY_COMPLEXITY).to_json()
+                ))
+            }
+        }
+        Ok(complexity)
+    }
+
+    fn field(&self, name: &str, first: &Option<Token<'a>>, children: &'a [Selection<'a>], page: usize, depth: usize, spreads: &mut Vec<&'a str>)
+        -> FieldResult<usize>
+    {
+        if depth > MAX_QUERY_DEPTH {
+            return Err(invalid_query(
+                Error::InvalidQuery.with("reason", "Query is too deep").with("max_depth", MAX_QUERY_DEPTH).to_json()
+            ))
+        }
+        let first = match *first {
+            Some(Token::Int(first)) => first.max(0) as usize,
+            Some(Token::Name(variable)) => self.variables[variable].as_u64().map_or(MAX_PAGE_SIZE, |first| first as usize),
+            _ => DEFAULT_PAGE_SIZE
+        };
+        let complexity = self.selections(children, first.min(MAX_PAGE_SIZE), depth, spreads)?;
+        Ok(match name {
+            "edges" => 1 + page.max(1).saturating_mul(complexity),
+            "totalCount" => MAX_PAGE_SIZE,
+            _ => 1 + complexity
+        })
+    }
+}
+
+
+fn parse_key(name: &str, hex: &str) -> FieldResult<PublicKey> {
+    PublicKey::from_hex(hex).map_err(|_| invalid_query(Error::InvalidQuery.with("reason", "Malformed public key").with(name, hex).to_json()))
+}
+
+
+fn parse_id(hex: &str) -> FieldResult<u64> {
+    hex.parse().map_err(|_| invalid_query(Error::InvalidQuery.with("reason", "Malformed member id").with("id", hex).to_json()))
+}
+
+
+// Delisted lots are left out of lot lists unless asked for explicitly.
+fn listed<T: AsRef<dyn Snapshot>>(schema: &AuctionSchema<T>, lot_pub_key: &PublicKey, include_delisted: Option<bool>) -> bool {
+    include_delisted.unwrap_or(false) || !schema.is_delisted(lot_pub_key)
+}
+
+
+fn invalid_query(description: String) -> FieldError {
+    FieldError::new(description, juniper::Value::null())
+}
+
+
+pub struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>
+}
+
+
+pub struct Edge<T> {
+    cursor: String,
+    node: T
+}
+
+
+// The total is counted only when asked for, since it walks the whole index.
+pub struct Connection<T> {
+    edges: Vec<Edge<T>>,
+    page_info: PageInfo,
+    total_count: Box<dyn Fn(&Context) -> usize>
+}
+
+
+fn page(first: Option<i32>, after: Option<String>) -> FieldResult<(usize, Option<PublicKey>)> {
+    let first = match first {
+        None => DEFAULT_PAGE_SIZE,
+        Some(first) if first >= 0 && first as usize <= MAX_PAGE_SIZE => first as usize,
+        Some(first) => return Err(invalid_query(
+            Error::InvalidQuery.with("reason", "Page size is out of range").with("first", first).with("max_page_size", MAX_PAGE_SIZE).to_json()
+        ))
+    };
+    let after = match after {
+        Some(ref cursor) => Some(parse_key("after", cursor)?),
+        None => None
+    };
+    Ok((first, after))
+}
+
+
+// Cursors are the hex public keys of the items, so they stay valid while new lots and bids arrive.
+// `keys` must already start at the cursor, so only one item past the page is ever read.
+fn paginate<T, I, F>(keys: I, first: usize, after: Option<PublicKey>, node: F, total_count: Box<dyn Fn(&Context) -> usize>) -> Connection<T>
+    where I: Iterator<Item = PublicKey>, F: Fn(&PublicKey) -> Option<T>
+{
+    let mut edges: Vec<_> = keys
+        .filter(|key| Some(*key) != after)
+        .filter_map(|key| node(&key).map(|node| Edge { cursor: key.to_hex(), node }))
+        .take(first + 1)
+        .collect();
+    let has_next_page = edges.len() > first;
+    edges.truncate(first);
+    let page_info = PageInfo {
+        has_next_page,
+        end_cursor: edges.last().map(|edge| edge.cursor.clone())
+    };
+    Connection { edges, page_info, total_count }
+}
+
+
+pub struct Seller {
+    pub_key: PublicKey
+}
+
+
+pub struct Member {
+    id: u64
+}
+
+
+pub struct Query;
+
+
+graphql_object!(Query: Context |&self| {
+    field lot(&executor, pub_key: String) -> FieldResult<Option<Lot>> {
+        Ok(executor.context().schema().lot(&parse_key("pub_key", &pub_key)?))
+    }
+
+    field lots(&executor, first: Option<i32>, after: Option<String>, include_delisted: Option<bool>) -> FieldResult<Connection<Lot>> {
+        let (first, after) = page(first, after)?;
+        let schema = executor.context().schema();
+        let lots = schema.lots();
+        let keys = match after { Some(ref after) => lots.keys_from(after), None => lots.keys() };
+        let node = |key: &PublicKey| schema.lot(key).filter(|_| listed(&schema, key, include_delisted));
+        Ok(paginate(keys, first, after, node, Box::new(move |context: &Context| {
+            let schema = context.schema();
+            let count = schema.lots().keys().filter(|key| listed(&schema, key, include_delisted)).count();
+            count
+        })))
+    }
+
+    field bid(&executor, pub_key: String) -> FieldResult<Option<Bid>> {
+        Ok(executor.context().schema().bid(&parse_key("pub_key", &pub_key)?))
+    }
+
+    field seller(pub_key: String) -> FieldResult<Seller> {
+        Ok(Seller { pub_key: parse_key("pub_key", &pub_key)? })
+    }
+
+    field member(id: String) -> FieldResult<Member> {
+        Ok(Member { id: parse_id(&id)? })
+    }
+});
+
+
+// u64 values are exposed as decimal strings, as in the REST API, since GraphQL `Int` is 32-bit.
+graphql_object!(Lot: Context |&self| {
+    field pub_key() -> String { self.pub_key().to_hex() }
+    field seller_pub_key() -> String { self.seller_pub_key().to_hex() }
+    field pub_session_key() -> String { self.pub_session_key().to_hex() }
+    field token_hash() -> String { self.token_hash().to_hex() }
+    field media_hashes() -> Vec<String> { self.media_hashes().iter().map(|hash| hash.to_hex()).collect() }
+    field description() -> &str { self.description() }
+    field price() -> String { self.price().to_string() }
+    field opened() -> bool { self.opened() }
+    field winner_member_id() -> String { self.winner_member_id().to_string() }
+    field min_reputation() -> String { self.min_reputation().to_string() }
+
+    field suspended(&executor) -> bool {
+        executor.context().schema().lot_moderation().get(self.pub_key()).map(|moderation| moderation.suspended()) == Some(true)
+    }
+
+    field delisted(&executor) -> bool {
+        executor.context().schema().is_delisted(self.pub_key())
+    }
+
+    field seller() -> Seller {
+        Seller { pub_key: *self.seller_pub_key() }
+    }
+
+    field winner() -> Option<Member> {
+        if self.opened() { None } else { Some(Member { id: self.winner_member_id() }) }
+    }
+
+    field bids(&executor, first: Option<i32>, after: Option<String>) -> FieldResult<Connection<Bid>> {
+        let (first, after) = page(first, after)?;
+        let schema = executor.context().schema();
+        let bid_keys = schema.lot_bid_keys(self.pub_key());
+        let keys = match after { Some(ref after) => bid_keys.iter_from(after), None => bid_keys.iter() };
+        let lot_pub_key = *self.pub_key();
+        Ok(paginate(keys, first, after, |key| schema.bid(key), Box::new(move |context: &Context| {
+            context.schema().lot_bid_keys(&lot_pub_key).iter().count()
+        })))
+    }
+});
+
+
+graphql_object!(Bid: Context |&self| {
+    field pub_key() -> String { self.pub_key().to_hex() }
+    field lot_pub_key() -> String { self.lot_pub_key().to_hex() }
+    field member_id() -> String { self.member_id().to_string() }
+    field sum() -> String { self.sum().to_string() }
+    field encrypted() -> bool { self.encrypted() }
+
+    field lot(&executor) -> Option<Lot> {
+        executor.context().schema().lot(self.lot_pub_key())
+    }
+
+    field member() -> Member {
+        Member { id: self.member_id() }
+    }
+});
+
+
+graphql_object!(Seller: Context |&self| {
+    field pub_key() -> String { self.pub_key.to_hex() }
+
+    field reputation(&executor) -> String {
+        executor.context().schema().reputation(&self.pub_key).score().to_string()
+    }
+
+    field banned(&executor) -> bool {
+        executor.context().schema().is_banned(&self.pub_key)
+    }
+
+    field average_rating(&executor) -> Option<f64> {
+        executor.context().schema().average_rating(&self.pub_key)
+    }
+
+    field lots(&executor, first: Option<i32>, after: Option<String>, include_delisted: Option<bool>) -> FieldResult<Connection<Lot>> {
+        let (first, after) = page(first, after)?;
+        let schema = executor.context().schema();
+        let lots = schema.seller_lots(&self.pub_key);
+        let keys = match after { Some(ref after) => lots.iter_from(after), None => lots.iter() };
+        let node = |key: &PublicKey| schema.lot(key).filter(|_| listed(&schema, key, include_delisted));
+        let seller_pub_key = self.pub_key;
+        Ok(paginate(keys, first, after, node, Box::new(move |context: &Context| {
+            let schema = context.schema();
+            let count = schema.seller_lots(&seller_pub_key).iter().filter(|key| listed(&schema, key, include_delisted)).count();
+            count
+        })))
+    }
+});
+
+
+graphql_object!(Member: Context |&self| {
+    field id() -> String { self.id.to_string() }
+
+    field won_lots(&executor, first: Option<i32>, after: Option<String>, include_delisted: Option<bool>) -> FieldResult<Connection<Lot>> {
+        let (first, after) = page(first, after)?;
+        let schema = executor.context().schema();
+        let lots = schema.member_won_lots(self.id);
+        let keys = match after { Some(ref after) => lots.iter_from(after), None => lots.iter() };
+        let node = |key: &PublicKey| schema.lot(key).filter(|_| listed(&schema, key, include_delisted));
+        let id = self.id;
+        Ok(paginate(keys, first, after, node, Box::new(move |context: &Context| {
+            let schema = context.schema();
+            let count = schema.member_won_lots(id).iter().filter(|key| listed(&schema, key, include_delisted)).count();
+            count
+        })))
+    }
+
+    field bids(&executor, first: Option<i32>, after: Option<String>) -> FieldResult<Connection<Bid>> {
+        let (first, after) = page(first, after)?;
+        let schema = executor.context().schema();
+        let bids = schema.member_bids(self.id);
+        let keys = match after { Some(ref after) => bids.iter_from(after), None => bids.iter() };
+        let id = self.id;
+        Ok(paginate(keys, first, after, |key| schema.bid(key), Box::new(move |context: &Context| {
+            context.schema().member_bids(id).iter().count()
+        })))
+    }
+});
+
+
+graphql_object!(PageInfo: Context |&self| {
+    field has_next_page() -> bool { self.has_next_page }
+    field end_cursor() -> &Option<String> { &self.end_cursor }
+});
+
+
+graphql_object!(Edge<Lot>: Context as "LotEdge" |&self| {
+    field cursor() -> &str { &self.cursor }
+    field node() -> &Lot { &self.node }
+});
+
+
+graphql_object!(Edge<Bid>: Context as "BidEdge" |&self| {
+    field cursor() -> &str { &self.cursor }
+    field node() -> &Bid { &self.node }
+});
+
+
+graphql_object!(Connection<Lot>: Context as "LotConnection" |&self| {
+    field edges() -> &Vec<Edge<Lot>> { &self.edges }
+    field page_info() -> &PageInfo { &self.page_info }
+    field total_count(&executor) -> i32 { (self.total_count)(executor.context()) as i32 }
+});
+
+
+graphql_object!(Connection<Bid>: Context as "BidConnection" |&self| {
+    field edges() -> &Vec<Edge<Bid>> { &self.edges }
+    field page_info() -> &PageInfo { &self.page_info }
+    field total_count(&executor) -> i32 { (self.total_count)(executor.context()) as i32 }
+});
@@ -1,25 +1,54 @@
+#![recursion_limit = "256"]
+
 #[macro_use]
 extern crate exonum;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate failure;
+#[macro_use]
+extern crate log;
+extern crate actix;
+extern crate actix_web;
+extern crate futures;
+extern crate serde_urlencoded;
+extern crate reqwest;
+#[macro_use]
+extern crate juniper;
+extern crate toml;
 
 use exonum::{
-    api::ServiceApiBuilder, blockchain::{Service, Transaction, TransactionSet},
+    api::ServiceApiBuilder, blockchain::{Service, ServiceContext, Transaction, TransactionSet},
     crypto::Hash,
-    encoding, messages::RawTransaction, storage::Snapshot
+    encoding, messages::RawTransaction, storage::{Fork, Snapshot}
 };
+use serde_json::Value;
 
 pub mod api;
 pub mod schema;
 pub mod transactions;
 pub mod errors;
 pub mod utils;
+pub mod media;
+pub mod consistency;
+pub mod spec;
+pub mod events;
+pub mod webhooks;
+pub mod graphql;
+pub mod config;
+pub mod client;
+pub mod routes;
 
-use api::AuctionApi;
-use transactions::AuctionTransactions;
+use api::{AuctionApi, ApiContext};
+use events::{EventHub, block_events};
+use webhooks::{RetryPolicy, WebhookQueue};
+use media::MediaConfig;
+use schema::{AuctionSchema, ServiceConfig};
+use config::LocalSettings;
+use transactions::{AuctionTransactions, index_lot_txs};
 
 
 pub const AUCTION_SERVICE_ID: u16 = 17;
@@ -27,8 +56,40 @@ pub const AUCTION_SERVICE_NAME: &str = "ex_auction";
 
 
 
-#[derive(Debug)]
-pub struct AuctionService;
+#[derive(Debug, Default)]
+pub struct AuctionService {
+    settings: LocalSettings,
+    events: EventHub,
+    webhooks: WebhookQueue,
+    config: ServiceConfig
+}
+
+
+impl AuctionService {
+    pub fn new(media_config: MediaConfig) -> Self {
+        AuctionService {
+            settings: LocalSettings { media: media_config, webhook_retry: RetryPolicy::default() },
+            events: EventHub::default(),
+            webhooks: WebhookQueue::default(),
+            config: ServiceConfig::default()
+        }
+    }
+
+    // Only takes effect when the genesis block is created; afterwards the stored config wins.
+    pub fn with_config(mut self, config: ServiceConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.settings.webhook_retry = retry_policy;
+        self
+    }
+
+    pub fn events(&self) -> &EventHub {
+        &self.events
+    }
+}
 
 
 impl Service for AuctionService {
@@ -44,12 +105,44 @@ impl Service for AuctionService {
         vec![]
     }
 
+    // The returned JSON ends up in the genesis configuration, so validators started with
+    // different service configs fail to agree on the genesis block.
+    fn initialize(&self, fork: &mut Fork) -> Value {
+        AuctionSchema::new(fork).init_config(&self.config);
+        serde_json::to_value(&self.config).unwrap()
+    }
+
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, encoding::Error> {
         let tx = AuctionTransactions::tx_from_raw(raw)?;
         Ok(tx.into())
     }
 
+    fn before_commit(&self, fork: &mut Fork) {
+        index_lot_txs(fork);
+        let mut schema = AuctionSchema::new(fork);
+        schema.commit_stats();
+        schema.activate_pending_config();
+    }
+
+    fn after_commit(&self, context: &ServiceContext) {
+        let schema = AuctionSchema::new(context.snapshot());
+        let webhooks: Vec<_> = webhooks::registered(&schema)
+            .into_iter()
+            .map(|(_, webhook)| webhook)
+            .collect();
+        if self.events.is_empty() && webhooks.is_empty() {
+            return
+        }
+        let events = block_events(context.snapshot(), context.height().0);
+        self.events.publish(&events);
+        self.webhooks.dispatch(&webhooks, &events, self.settings.effective(&schema).webhook_retry);
+    }
+
     fn wire_api(&self, builder: &mut ServiceApiBuilder) {
-        AuctionApi::wire(builder);
+        AuctionApi::wire(builder, ApiContext {
+            settings: self.settings.clone(),
+            events: self.events.clone(),
+            webhooks: self.webhooks.clone()
+        });
     }
 }
@@ -1,57 +1,142 @@
 extern crate exonum;
 extern crate ex_auction;
+extern crate clap;
+extern crate failure;
 
-use exonum::blockchain::{GenesisConfig, ValidatorKeys};
-use exonum::node::{Node, NodeApiConfig, NodeConfig};
-use exonum::storage::MemoryDB;
+use std::net::SocketAddr;
+use std::path::Path;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use exonum::helpers::config::ConfigFile;
+use exonum::node::{Node, NodeConfig};
 
-use ex_auction::AuctionService;
+use ex_auction::config::{
+    self, AuctionConfig, NodeKeys, NodeTemplate, Storage, DEFAULT_TESTNET_API_PORT, DEFAULT_TESTNET_PEER_PORT
+};
 
 
-fn node_config() -> NodeConfig {
-    let (consensus_public_key, consensus_secret_key) = exonum::crypto::gen_keypair();
-    let (service_public_key, service_secret_key) = exonum::crypto::gen_keypair();
+fn address(matches: &ArgMatches, name: &str, default: SocketAddr) -> Result<SocketAddr, failure::Error> {
+    match matches.value_of(name) {
+        Some(address) => Ok(address.parse()?),
+        None => Ok(default)
+    }
+}
 
-    let validator_keys = ValidatorKeys {
-        consensus_key: consensus_public_key,
-        service_key: service_public_key,
-    };
-    let genesis = GenesisConfig::new(vec![validator_keys].into_iter());
 
-    let api_address = "0.0.0.0:8000".parse().unwrap();
-    let api_cfg = NodeApiConfig {
-        public_api_address: Some(api_address),
-        ..Default::default()
+fn generate_keys(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let output = matches.value_of("OUTPUT").unwrap();
+    ConfigFile::save(&NodeKeys::generate(), output)?;
+    println!("Keys are written to {}", output);
+    Ok(())
+}
+
+
+fn generate_template(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let keys = match matches.value_of("keys") {
+        Some(path) => ConfigFile::load(path)?,
+        None => NodeKeys::generate()
     };
+    let mut template = NodeTemplate::new(keys);
+    template.peer_address = address(matches, "peer-address", template.peer_address)?;
+    template.public_api_address = address(matches, "public-api-address", template.public_api_address)?;
+    template.private_api_address = address(matches, "private-api-address", template.private_api_address)?;
+    let output = matches.value_of("OUTPUT").unwrap();
+    ConfigFile::save(&template.build()?, output)?;
+    println!("Node config is written to {}", output);
+    Ok(())
+}
 
-    let peer_address = "0.0.0.0:2000".parse().unwrap();
-
-    NodeConfig {
-        listen_address: peer_address,
-        service_public_key,
-        service_secret_key,
-        consensus_public_key,
-        consensus_secret_key,
-        genesis,
-        external_address: Some(peer_address),
-        network: Default::default(),
-        connect_list: Default::default(),
-        api: api_cfg,
-        mempool: Default::default(),
-        services_configs: Default::default(),
-        database: Default::default(),
+
+fn generate_testnet(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let port = |name: &str, default: u16| matches.value_of(name).map_or(Ok(default), str::parse);
+    let count: u16 = matches.value_of("validators").unwrap().parse()?;
+    let dir = Path::new(matches.value_of("OUTPUT_DIR").unwrap());
+    let configs = config::testnet(count, port("peer-port", DEFAULT_TESTNET_PEER_PORT)?, port("api-port", DEFAULT_TESTNET_API_PORT)?)?;
+    for (validator, node_config) in configs.iter().enumerate() {
+        ConfigFile::save(node_config, config::testnet_node_config(dir, validator as u16))?;
     }
+    println!("Configs of {} validators are written to {}", count, dir.display());
+    Ok(())
+}
+
+
+fn run_node(node_config: NodeConfig, storage: &Storage) -> Result<(), failure::Error> {
+    let service = AuctionConfig::from_node_config(&node_config)?.service();
+    let database = storage.open(&node_config.database)?;
+    let node = Node::new(database, vec![Box::new(service)], node_config, None);
+    println!("Blockchain is ready for transactions!");
+    node.run()
 }
 
+
+fn run(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let storage = match matches.value_of("db-path") {
+        Some(path) => Storage::RocksDb(path.into()),
+        None => Storage::Memory
+    };
+    run_node(ConfigFile::load(matches.value_of("node-config").unwrap())?, &storage)
+}
+
+
+fn run_validator(matches: &ArgMatches) -> Result<(), failure::Error> {
+    let dir = Path::new(matches.value_of("TESTNET_DIR").unwrap());
+    let validator: u16 = matches.value_of("VALIDATOR").unwrap().parse()?;
+    let storage = if matches.is_present("in-memory") {
+        Storage::Memory
+    } else {
+        Storage::RocksDb(config::testnet_db_path(dir, validator))
+    };
+    run_node(ConfigFile::load(config::testnet_node_config(dir, validator))?, &storage)
+}
+
+
 fn main() {
     exonum::helpers::init_logger().unwrap();
 
-    let node = Node::new(
-        MemoryDB::new(),
-        vec![Box::new(AuctionService)],
-        node_config(),
-        None,
-    );
-    println!("Blockchain is ready for transactions!");
-    node.run().unwrap();
+    let address_arg = |name: &'static str, help: &'static str| Arg::with_name(name).long(name).takes_value(true).help(help);
+    let matches = App::new("ex_auction")
+        .about("Exonum auction node")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("generate-keys")
+            .about("Generate consensus and service keypairs")
+            .arg(Arg::with_name("OUTPUT").required(true).help("Path to write the keys to")))
+        .subcommand(SubCommand::with_name("generate-template")
+            .about("Generate a node config to edit, with a single-validator genesis and default service settings")
+            .arg(Arg::with_name("OUTPUT").required(true).help("Path to write the node config to"))
+            .arg(Arg::with_name("keys").long("keys").takes_value(true).help("Keys from generate-keys; fresh keys by default"))
+            .arg(address_arg("peer-address", "Address to listen to peers on"))
+            .arg(address_arg("public-api-address", "Address of the public API"))
+            .arg(address_arg("private-api-address", "Address of the private API")))
+        .subcommand(SubCommand::with_name("run")
+            .about("Run the node")
+            .arg(Arg::with_name("node-config").short("c").long("node-config").takes_value(true).required(true).help("Path to the node config"))
+            .arg(Arg::with_name("db-path").short("d").long("db-path").takes_value(true).required_unless("in-memory")
+                .help("Path to the RocksDB database, created on first start"))
+            .arg(Arg::with_name("in-memory").long("in-memory").conflicts_with("db-path")
+                .help("Keep the blockchain in memory only; it is lost when the node stops")))
+        .subcommand(SubCommand::with_name("generate-testnet")
+            .about("Generate configs of a local network where every node is a validator")
+            .arg(Arg::with_name("OUTPUT_DIR").required(true).help("Directory to write a config per validator to"))
+            .arg(Arg::with_name("validators").short("n").long("validators").takes_value(true).required(true).help("Number of validators"))
+            .arg(Arg::with_name("peer-port").long("peer-port").takes_value(true).help("Peer port of the first validator, the others follow it"))
+            .arg(Arg::with_name("api-port").long("api-port").takes_value(true).help("Public API port of the first validator, the others follow it")))
+        .subcommand(SubCommand::with_name("run-validator")
+            .about("Run a validator of a network from generate-testnet")
+            .arg(Arg::with_name("TESTNET_DIR").required(true).help("Directory passed to generate-testnet"))
+            .arg(Arg::with_name("VALIDATOR").required(true).help("Index of the validator, starting from 0"))
+            .arg(Arg::with_name("in-memory").long("in-memory")
+                .help("Keep the blockchain in memory instead of the validator's directory")))
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("generate-keys", Some(matches)) => generate_keys(matches),
+        ("generate-template", Some(matches)) => generate_template(matches),
+        ("run", Some(matches)) => run(matches),
+        ("generate-testnet", Some(matches)) => generate_testnet(matches),
+        ("run-validator", Some(matches)) => run_validator(matches),
+        _ => unreachable!()
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
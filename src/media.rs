@@ -0,0 +1,57 @@
+use exonum::crypto::{CryptoHash, Hash};
+
+
+pub const DEFAULT_MAX_MEDIA_SIZE: usize = 5 * 1024 * 1024;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MediaConfig {
+    pub max_size: usize,
+    pub mime_types: Vec<String>
+}
+
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        MediaConfig {
+            max_size: DEFAULT_MAX_MEDIA_SIZE,
+            mime_types: vec![
+                "image/jpeg".to_owned(),
+                "image/png".to_owned(),
+                "application/pdf".to_owned(),
+            ]
+        }
+    }
+}
+
+
+impl MediaConfig {
+    pub fn check(&self, mime_type: &str, data: &[u8]) -> Result<(), String> {
+        if data.is_empty() {
+            return Err("Media is empty".to_owned())
+        }
+        if data.len() > self.max_size {
+            return Err(format!("Media exceeds {} bytes", self.max_size))
+        }
+        if !self.mime_types.iter().any(|allowed| allowed == mime_type) {
+            return Err(format!("Media type {} is not allowed", mime_type))
+        }
+        Ok(())
+    }
+}
+
+
+encoding_struct! {
+    struct Blob {
+        mime_type: &str,
+        data: Vec<u8>
+    }
+}
+
+
+impl Blob {
+    // Covers the MIME type as well, so the same bytes uploaded as different types get different hashes.
+    pub fn content_hash(&self) -> Hash {
+        self.hash()
+    }
+}
@@ -0,0 +1,164 @@
+use exonum::api::{self, Immutable, Mutable, NamedWith, ServiceApiBackend, ServiceApiBuilder, ServiceApiState};
+use exonum::api::backends::actix::RequestHandler;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use api::ApiContext;
+use spec::{ApiSchema, Components};
+
+
+// Versions are only ever appended. Each one mounts its complete route table under its own
+// prefix, so a `v2` is a new entry with its own table while older clients keep working.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiVersion {
+    pub name: &'static str,
+    pub deprecated: bool,
+    // Unprefixed paths that stay mounted as aliases of this version's routes.
+    pub legacy_aliases: &'static [&'static str],
+    pub routes: fn(&ApiContext) -> ApiRoutes
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VersionInfo {
+    pub name: String,
+    pub deprecated: bool,
+    pub routes: Vec<String>,
+    pub legacy_aliases: Vec<String>
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiScope {
+    Public,
+    Private
+}
+
+
+// Everything `spec` needs to document a route, taken from its handler's types.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteDoc {
+    pub tag: &'static str,
+    pub summary: &'static str,
+    pub query: fn(&mut Components) -> Value,
+    pub response: fn(&mut Components) -> Value,
+    // The response is a WebSocket stream of `response` messages.
+    pub stream: bool
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub scope: ApiScope,
+    pub handler: RequestHandler,
+    pub doc: RouteDoc
+}
+
+
+// Route table of a single version; paths are relative to the version prefix.
+#[derive(Debug)]
+pub struct ApiRoutes {
+    scope: ApiScope,
+    tag: &'static str,
+    routes: Vec<Route>
+}
+
+
+impl Default for ApiRoutes {
+    fn default() -> Self {
+        ApiRoutes { scope: ApiScope::Public, tag: "", routes: Vec::new() }
+    }
+}
+
+
+impl ApiRoutes {
+    // Routes added after this call are served on the public API.
+    pub fn public(&mut self) -> &mut Self {
+        self.scope = ApiScope::Public;
+        self
+    }
+
+    // Routes added after this call are served on the private API.
+    pub fn private(&mut self) -> &mut Self {
+        self.scope = ApiScope::Private;
+        self
+    }
+
+    // Groups the routes added after this call in the spec.
+    pub fn tag(&mut self, tag: &'static str) -> &mut Self {
+        self.tag = tag;
+        self
+    }
+
+    pub fn get<Q, I, F>(&mut self, path: &str, handler: F, summary: &'static str) -> &mut Self
+    where
+        Q: DeserializeOwned + ApiSchema + 'static,
+        I: Serialize + ApiSchema + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> api::Result<I> + 'static + Send + Sync + Clone
+    {
+        let handler = NamedWith::<Q, I, api::Result<I>, F, Immutable>::new(path, handler).into();
+        self.route(handler, self.doc::<Q, I>(summary, false))
+    }
+
+    pub fn post<Q, I, F>(&mut self, path: &str, handler: F, summary: &'static str) -> &mut Self
+    where
+        Q: DeserializeOwned + ApiSchema + 'static,
+        I: Serialize + ApiSchema + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> api::Result<I> + 'static + Send + Sync + Clone
+    {
+        let handler = NamedWith::<Q, I, api::Result<I>, F, Mutable>::new(path, handler).into();
+        self.route(handler, self.doc::<Q, I>(summary, false))
+    }
+
+    // A raw handler upgrading to a WebSocket that pushes `M` messages, filtered by a `Q` query.
+    pub fn stream<Q: ApiSchema, M: ApiSchema>(&mut self, handler: RequestHandler, summary: &'static str) -> &mut Self {
+        let doc = self.doc::<Q, M>(summary, true);
+        self.route(handler, doc)
+    }
+
+    fn doc<Q: ApiSchema, I: ApiSchema>(&self, summary: &'static str, stream: bool) -> RouteDoc {
+        RouteDoc { tag: self.tag, summary, query: Q::schema, response: I::schema, stream }
+    }
+
+    fn route(&mut self, handler: RequestHandler, doc: RouteDoc) -> &mut Self {
+        self.routes.push(Route { scope: self.scope, handler, doc });
+        self
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    pub fn mount(&self, builder: &mut ServiceApiBuilder, version: &ApiVersion) {
+        for route in &self.routes {
+            let backend = match route.scope {
+                ApiScope::Public => builder.public_scope().web_backend(),
+                ApiScope::Private => builder.private_scope().web_backend()
+            };
+            if version.legacy_aliases.contains(&route.handler.name.as_str()) {
+                backend.raw_handler(route.handler.clone());
+            }
+            let mut versioned = route.handler.clone();
+            versioned.name = format!("{}/{}", version.name, route.handler.name);
+            backend.raw_handler(versioned);
+        }
+    }
+
+    // Only public routes are listed, since the listing itself is public.
+    pub fn info(&self, version: &ApiVersion) -> VersionInfo {
+        let public: Vec<&RequestHandler> = self.routes.iter()
+            .filter(|route| route.scope == ApiScope::Public)
+            .map(|route| &route.handler)
+            .collect();
+        let mut legacy_aliases: Vec<String> = public.iter()
+            .filter(|handler| version.legacy_aliases.contains(&handler.name.as_str()))
+            .map(|handler| handler.name.clone())
+            .collect();
+        legacy_aliases.dedup();
+        VersionInfo {
+            name: version.name.to_owned(),
+            deprecated: version.deprecated,
+            routes: public.iter().map(|handler| format!("{} {}/{}", handler.method, version.name, handler.name)).collect(),
+            legacy_aliases
+        }
+    }
+}
@@ -2,30 +2,45 @@ extern crate exonum_sodiumoxide;
 
 use std::mem;
 use exonum::{
-    crypto::{PublicKey, Hash, SecretKey}, storage::{Fork, MapIndex, ListIndex, Snapshot},
-    helpers
+    blockchain::Schema, crypto::{PublicKey, Hash, SecretKey},
+    storage::{Fork, Entry, MapIndex, ListIndex, KeySetIndex, Snapshot}, helpers
 };
 use serde_json;
-use schema::exonum_sodiumoxide::crypto;
+use schema::exonum_sodiumoxide::crypto::{self, sign::ed25519};
 use utils::{hex_to_bytes, from_slice};
+use media::Blob;
 
 
-encoding_struct! {
-    struct Lot {
-        pub_key: &PublicKey,
-        pub_session_key: &PublicKey,
-        token_hash: &Hash,
-        description: &str,
-        price: u64,
-        opened: bool,
-        winner_member_id: u64
+// The generated `new` takes every field, which is more than clippy allows; the module keeps the
+// allowance off the hand-written code.
+mod lot {
+    #![allow(clippy::too_many_arguments)]
+    use exonum::crypto::{PublicKey, Hash};
+
+    encoding_struct! {
+        struct Lot {
+            pub_key: &PublicKey,
+            seller_pub_key: &PublicKey,
+            pub_session_key: &PublicKey,
+            token_hash: &Hash,
+            media_hashes: &[Hash],
+            description: &str,
+            price: u64,
+            opened: bool,
+            winner_member_id: u64,
+            min_reputation: u64
+        }
     }
 }
+pub use self::lot::Lot;
 
 
 impl Lot {
     pub fn close(self, winner_member_id: u64, price: u64) -> Self {
-        Self::new(self.pub_key(), self.pub_session_key(), self.token_hash(), self.description(), price, false, winner_member_id)
+        Self::new(
+            self.pub_key(), self.seller_pub_key(), self.pub_session_key(), self.token_hash(), self.media_hashes(), self.description(),
+            price, false, winner_member_id, self.min_reputation()
+        )
     }
 }
 
@@ -48,35 +63,37 @@ struct BidData {
 }
 
 
+// Session keys are ed25519 signing keys, so sealed boxes use their curve25519 counterparts.
+fn box_public_key(pub_session_key: &PublicKey) -> crypto::box_::PublicKey {
+    crypto::box_::PublicKey(ed25519::convert_ed_pk_to_curve25519(&from_slice(pub_session_key.as_ref())))
+}
+
+
+// Size of `crypto_box_SEALBYTES`, the overhead every sealed bid carries.
+const SEAL_BYTES: usize = 48;
+
+
 impl Bid {
-    pub fn decrypt(self, pub_session_key: &PublicKey, secret_session_key_hex: &str) -> Bid {
-        let pub_session_key_bytes = hex_to_bytes(&pub_session_key.to_hex());
-        let secret_session_key_bytes = hex_to_bytes(secret_session_key_hex);
-        let pub_session_key = crypto::box_::PublicKey(from_slice(&pub_session_key_bytes));
-        let secret_session_key = crypto::box_::SecretKey(from_slice(&secret_session_key_bytes));
+    pub fn is_sealed(&self) -> bool {
+        self.encrypted() && self.data().len() > SEAL_BYTES
+    }
+
+    // `None` if the bid was not sealed for this session key or holds no valid bid data.
+    pub fn decrypt(self, pub_session_key: &PublicKey, secret_session_key: &SecretKey) -> Option<Bid> {
+        let secret_session_key = ed25519::convert_ed_sk_to_curve25519(&from_slice(&hex_to_bytes(&secret_session_key.to_hex())));
         let data = crypto::sealedbox::open(
             &self.data(),
-            &pub_session_key,
-            &secret_session_key
-        );
-        match data {
-            Err(e) => {
-                println!("{:?}", e);
-                self
-            },
-            Ok(v) => {
-                let data: BidData = serde_json::from_slice(&v).unwrap();
-                Self::new(self.pub_key(), self.lot_pub_key(), data.member_id, data.sum, false, vec![])
-            }
-        }
+            &box_public_key(pub_session_key),
+            &crypto::box_::SecretKey(secret_session_key)
+        ).ok()?;
+        let data: BidData = serde_json::from_slice(&data).ok()?;
+        Some(Self::new(self.pub_key(), self.lot_pub_key(), data.member_id, data.sum, false, vec![]))
     }
 
     pub fn encrypt(self, pub_session_key: &PublicKey) -> Bid {
-        let pub_session_key_bytes = hex_to_bytes(&pub_session_key.to_hex());
-        let pub_session_key = crypto::box_::PublicKey(from_slice(&pub_session_key_bytes));
         let data = BidData {member_id: self.member_id(), sum: self.sum()};
         let data = serde_json::to_vec(&data).unwrap();
-        let data = crypto::sealedbox::seal(&data, &pub_session_key);
+        let data = crypto::sealedbox::seal(&data, &box_public_key(pub_session_key));
         Self::new(self.pub_key(), self.lot_pub_key(), 0, 0, true, data)
         /*unsafe {
             let pub_session_key = mem::transmute::<PublicKey, crypto::box_::PublicKey>(*pub_session_key);
@@ -90,6 +107,275 @@ impl Bid {
 }
 
 
+encoding_struct! {
+    struct LotRecord {
+        height: u64,
+        tx_hash: &Hash,
+        lot: Lot
+    }
+}
+
+
+encoding_struct! {
+    struct BidRecord {
+        height: u64,
+        tx_hash: &Hash,
+        bid: Bid
+    }
+}
+
+
+encoding_struct! {
+    struct AuctionStats {
+        lots: u64,
+        closed_lots: u64,
+        sold_lots: u64,
+        bids: u64,
+        volume: u64,
+        reserve: u64
+    }
+}
+
+
+impl Default for AuctionStats {
+    fn default() -> Self {
+        Self::new(0, 0, 0, 0, 0, 0)
+    }
+}
+
+
+impl AuctionStats {
+    pub fn open_lots(&self) -> u64 {
+        self.lots() - self.closed_lots()
+    }
+
+    pub fn add_lot(self) -> Self {
+        Self::new(self.lots() + 1, self.closed_lots(), self.sold_lots(), self.bids(), self.volume(), self.reserve())
+    }
+
+    pub fn add_bid(self) -> Self {
+        Self::new(self.lots(), self.closed_lots(), self.sold_lots(), self.bids() + 1, self.volume(), self.reserve())
+    }
+
+    pub fn add_outcome(self, outcome: &LotOutcome) -> Self {
+        if outcome.price() == 0 {
+            return Self::new(self.lots(), self.closed_lots() + 1, self.sold_lots(), self.bids(), self.volume(), self.reserve())
+        }
+        Self::new(
+            self.lots(), self.closed_lots() + 1, self.sold_lots() + 1, self.bids(),
+            self.volume() + outcome.price(), self.reserve() + outcome.reserve()
+        )
+    }
+}
+
+
+encoding_struct! {
+    struct LotOutcome {
+        height: u64,
+        lot_pub_key: &PublicKey,
+        seller_pub_key: &PublicKey,
+        reserve: u64,
+        price: u64,
+        bids: u64
+    }
+}
+
+
+pub const DEFAULT_MAX_DESCRIPTION_LENGTH: u64 = 4096;
+
+
+// Parameters every validator must agree on, fixed in the genesis block. Stored as JSON, the way
+// exonum's configuration service keeps its configs. Only `admin_keys`, `max_description_length`
+// (zero means no limit) and `vote_threshold` take effect. `listing_fee`, `sale_fee_bp` (basis
+// points of the sale price), `min_bid_increment` and `default_deadline` (in blocks) are stored and
+// served for clients, but no transaction enforces them yet.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ServiceConfig {
+    pub admin_keys: Vec<PublicKey>,
+    pub listing_fee: u64,
+    pub sale_fee_bp: u64,
+    pub min_bid_increment: u64,
+    pub max_description_length: u64,
+    pub default_deadline: u64,
+    // Votes a config proposal needs; zero means a majority of `admin_keys`.
+    pub vote_threshold: u64
+}
+
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        ServiceConfig {
+            admin_keys: Vec::new(),
+            listing_fee: 0,
+            sale_fee_bp: 0,
+            min_bid_increment: 0,
+            max_description_length: DEFAULT_MAX_DESCRIPTION_LENGTH,
+            default_deadline: 0,
+            vote_threshold: 0
+        }
+    }
+}
+
+
+impl ServiceConfig {
+    pub fn is_admin(&self, pub_key: &PublicKey) -> bool {
+        self.admin_keys.contains(pub_key)
+    }
+
+    pub fn required_votes(&self) -> u64 {
+        if self.vote_threshold > 0 {
+            self.vote_threshold
+        } else {
+            self.admin_keys.len() as u64 / 2 + 1
+        }
+    }
+}
+
+
+// Latest moderation decision about a lot. A suspension can be lifted, a delisting is final.
+encoding_struct! {
+    struct LotModeration {
+        suspended: bool,
+        delisted: bool,
+        reason: &str,
+        admin: &PublicKey,
+        height: u64
+    }
+}
+
+
+// Latest ban decision about a member key; `banned: false` records a lifted ban.
+encoding_struct! {
+    struct MemberBan {
+        banned: bool,
+        reason: &str,
+        admin: &PublicKey,
+        height: u64
+    }
+}
+
+
+// A failed deal outweighs a completed one.
+pub const REPUTATION_PENALTY: i64 = 2;
+
+
+// Deal counters of a member key, either as a seller or as a bidder.
+encoding_struct! {
+    struct Reputation {
+        sales: u64,
+        purchases: u64,
+        non_payments: u64,
+        failed_deliveries: u64
+    }
+}
+
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Self::new(0, 0, 0, 0)
+    }
+}
+
+
+impl Reputation {
+    pub fn score(&self) -> i64 {
+        let penalties = (self.non_payments() + self.failed_deliveries()) as i64;
+        (self.sales() + self.purchases()) as i64 - REPUTATION_PENALTY * penalties
+    }
+
+    pub fn add_outcome(self, outcome: SettlementOutcome, seller: bool) -> Self {
+        let (sales, purchases, non_payments, failed_deliveries) = (self.sales(), self.purchases(), self.non_payments(), self.failed_deliveries());
+        match (outcome, seller) {
+            (SettlementOutcome::Completed, true) => Self::new(sales + 1, purchases, non_payments, failed_deliveries),
+            (SettlementOutcome::Completed, false) => Self::new(sales, purchases + 1, non_payments, failed_deliveries),
+            (SettlementOutcome::NonPayment, false) => Self::new(sales, purchases, non_payments + 1, failed_deliveries),
+            (SettlementOutcome::FailedDelivery, true) => Self::new(sales, purchases, non_payments, failed_deliveries + 1),
+            _ => self
+        }
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum SettlementOutcome {
+    Completed = 0,
+    NonPayment = 1,
+    FailedDelivery = 2
+}
+
+
+impl SettlementOutcome {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SettlementOutcome::Completed),
+            1 => Some(SettlementOutcome::NonPayment),
+            2 => Some(SettlementOutcome::FailedDelivery),
+            _ => None
+        }
+    }
+}
+
+
+// How the deal behind a closed lot ended, as reported by an administrator.
+encoding_struct! {
+    struct Settlement {
+        winner: &PublicKey,
+        outcome: u8,
+        admin: &PublicKey,
+        height: u64
+    }
+}
+
+
+pub const MAX_RATING: u8 = 5;
+pub const MAX_REVIEW_LENGTH: usize = 280;
+
+
+// One party's rating of the other after a lot settles, from 1 to `MAX_RATING`.
+encoding_struct! {
+    struct Rating {
+        lot_pub_key: &PublicKey,
+        from: &PublicKey,
+        to: &PublicKey,
+        rating: u8,
+        review: &str,
+        height: u64
+    }
+}
+
+
+// `config` is the proposed `ServiceConfig` as JSON. An accepted proposal becomes the pending
+// config, replacing any other pending one, and is activated at `actual_from`.
+encoding_struct! {
+    struct ConfigProposal {
+        proposer: &PublicKey,
+        config: &str,
+        actual_from: u64,
+        accepted: bool
+    }
+}
+
+
+impl ConfigProposal {
+    pub fn accept(self) -> Self {
+        Self::new(self.proposer(), self.config(), self.actual_from(), true)
+    }
+}
+
+
+// One entry per activated config, starting with the genesis one at height 0.
+encoding_struct! {
+    struct ConfigRecord {
+        height: u64,
+        proposal_hash: &Hash,
+        config: &str
+    }
+}
+
+
 #[derive(Debug)]
 pub struct AuctionSchema<T> {
     view: T
@@ -120,6 +406,214 @@ impl<T: AsRef<dyn Snapshot>> AuctionSchema<T> {
     pub fn bid(&self, pub_key: &PublicKey) -> Option<Bid> {
         self.bids().get(pub_key)
     }
+
+    // Secondary indexes ordered by key, so that lists can be paged with `iter_from`.
+    pub fn lot_bid_keys(&self, lot_pub_key: &PublicKey) -> KeySetIndex<&dyn Snapshot, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.lot_bid_keys", lot_pub_key, self.view.as_ref())
+    }
+
+    pub fn seller_lots(&self, seller_pub_key: &PublicKey) -> KeySetIndex<&dyn Snapshot, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.seller_lots", seller_pub_key, self.view.as_ref())
+    }
+
+    pub fn member_won_lots(&self, member_id: u64) -> KeySetIndex<&dyn Snapshot, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.member_won_lots", &member_id, self.view.as_ref())
+    }
+
+    // Only revealed bids, since sealed ones do not show their member.
+    pub fn member_bids(&self, member_id: u64) -> KeySetIndex<&dyn Snapshot, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.member_bids", &member_id, self.view.as_ref())
+    }
+
+    pub fn lot_txs(&self, lot_pub_key: &PublicKey) -> ListIndex<&dyn Snapshot, Hash> {
+        ListIndex::new_in_family("ex_auction.lot_txs", lot_pub_key, self.view.as_ref())
+    }
+
+    pub fn lot_history(&self, pub_key: &PublicKey) -> ListIndex<&dyn Snapshot, LotRecord> {
+        ListIndex::new_in_family("ex_auction.lot_history", pub_key, self.view.as_ref())
+    }
+
+    pub fn bid_history(&self, pub_key: &PublicKey) -> ListIndex<&dyn Snapshot, BidRecord> {
+        ListIndex::new_in_family("ex_auction.bid_history", pub_key, self.view.as_ref())
+    }
+
+    pub fn lot_at(&self, pub_key: &PublicKey, height: u64) -> Option<Lot> {
+        let history: Vec<LotRecord> = self.lot_history(pub_key).iter().collect();
+        history.into_iter().rev()
+            .find(|record| record.height() <= height)
+            .map(|record| record.lot())
+    }
+
+    pub fn bid_at(&self, pub_key: &PublicKey, height: u64) -> Option<Bid> {
+        let history: Vec<BidRecord> = self.bid_history(pub_key).iter().collect();
+        history.into_iter().rev()
+            .find(|record| record.height() <= height)
+            .map(|record| record.bid())
+    }
+
+    pub fn stats(&self) -> AuctionStats {
+        Entry::new("ex_auction.stats", self.view.as_ref()).get().unwrap_or_default()
+    }
+
+    pub fn stats_history(&self) -> MapIndex<&dyn Snapshot, u64, AuctionStats> {
+        MapIndex::new("ex_auction.stats_history", self.view.as_ref())
+    }
+
+    pub fn stats_at(&self, height: u64) -> AuctionStats {
+        self.stats_history().get(&height).unwrap_or_else(|| {
+            if height >= Schema::new(self.view.as_ref()).height().0 {
+                self.stats()
+            } else {
+                AuctionStats::default()
+            }
+        })
+    }
+
+    pub fn lot_outcomes(&self) -> ListIndex<&dyn Snapshot, LotOutcome> {
+        ListIndex::new("ex_auction.lot_outcomes", self.view.as_ref())
+    }
+
+    pub fn lot_outcomes_between(&self, from: u64, to: u64) -> Vec<LotOutcome> {
+        let outcomes = self.lot_outcomes();
+        let (mut low, mut high) = (0, outcomes.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            if outcomes.get(middle).unwrap().height() < from {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        outcomes.iter_from(low).take_while(|outcome| outcome.height() <= to).collect()
+    }
+
+    pub fn media(&self) -> KeySetIndex<&dyn Snapshot, Hash> {
+        KeySetIndex::new("ex_auction.media", self.view.as_ref())
+    }
+
+    pub fn config(&self) -> ServiceConfig {
+        let config: Option<String> = Entry::new("ex_auction.config", self.view.as_ref()).get();
+        config.and_then(|config| serde_json::from_str(&config).ok()).unwrap_or_default()
+    }
+
+    pub fn lot_moderation(&self) -> MapIndex<&dyn Snapshot, PublicKey, LotModeration> {
+        MapIndex::new("ex_auction.lot_moderation", self.view.as_ref())
+    }
+
+    pub fn is_delisted(&self, lot_pub_key: &PublicKey) -> bool {
+        self.lot_moderation().get(lot_pub_key).map(|moderation| moderation.delisted()) == Some(true)
+    }
+
+    pub fn member_bans(&self) -> MapIndex<&dyn Snapshot, PublicKey, MemberBan> {
+        MapIndex::new("ex_auction.member_bans", self.view.as_ref())
+    }
+
+    pub fn is_banned(&self, pub_key: &PublicKey) -> bool {
+        self.member_bans().get(pub_key).map(|ban| ban.banned()) == Some(true)
+    }
+
+    pub fn reputations(&self) -> MapIndex<&dyn Snapshot, PublicKey, Reputation> {
+        MapIndex::new("ex_auction.reputations", self.view.as_ref())
+    }
+
+    pub fn reputation(&self, pub_key: &PublicKey) -> Reputation {
+        self.reputations().get(pub_key).unwrap_or_default()
+    }
+
+    // Member key that signed each bid.
+    pub fn bid_members(&self) -> MapIndex<&dyn Snapshot, PublicKey, PublicKey> {
+        MapIndex::new("ex_auction.bid_members", self.view.as_ref())
+    }
+
+    // Member key behind the winning bid of each closed lot that was sold.
+    pub fn lot_winners(&self) -> MapIndex<&dyn Snapshot, PublicKey, PublicKey> {
+        MapIndex::new("ex_auction.lot_winners", self.view.as_ref())
+    }
+
+    pub fn settlements(&self) -> MapIndex<&dyn Snapshot, PublicKey, Settlement> {
+        MapIndex::new("ex_auction.settlements", self.view.as_ref())
+    }
+
+    // Ratings of a lot keyed by the party who gave them, so that each party rates once.
+    pub fn lot_ratings(&self, lot_pub_key: &PublicKey) -> MapIndex<&dyn Snapshot, PublicKey, Rating> {
+        MapIndex::new_in_family("ex_auction.lot_ratings", lot_pub_key, self.view.as_ref())
+    }
+
+    // Ratings a member has received, oldest first.
+    pub fn member_ratings(&self, pub_key: &PublicKey) -> ListIndex<&dyn Snapshot, Rating> {
+        ListIndex::new_in_family("ex_auction.member_ratings", pub_key, self.view.as_ref())
+    }
+
+    pub fn average_rating(&self, pub_key: &PublicKey) -> Option<f64> {
+        let ratings = self.member_ratings(pub_key);
+        if ratings.is_empty() {
+            return None
+        }
+        let total: u64 = ratings.iter().map(|rating| u64::from(rating.rating())).sum();
+        Some(total as f64 / ratings.len() as f64)
+    }
+
+    pub fn config_proposals(&self) -> MapIndex<&dyn Snapshot, Hash, ConfigProposal> {
+        MapIndex::new("ex_auction.config_proposals", self.view.as_ref())
+    }
+
+    pub fn config_votes(&self, proposal_hash: &Hash) -> KeySetIndex<&dyn Snapshot, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.config_votes", proposal_hash, self.view.as_ref())
+    }
+
+    // Hash of the accepted proposal waiting for its `actual_from`; at most one at a time, the latest accepted wins.
+    pub fn pending_config(&self) -> Option<Hash> {
+        Entry::new("ex_auction.pending_config", self.view.as_ref()).get()
+    }
+
+    pub fn config_history(&self) -> ListIndex<&dyn Snapshot, ConfigRecord> {
+        ListIndex::new("ex_auction.config_history", self.view.as_ref())
+    }
+
+    // Node-local storage, filled through the API and never touched by transactions.
+    pub fn blobs(&self) -> MapIndex<&dyn Snapshot, Hash, Blob> {
+        MapIndex::new("ex_auction.local.blobs", self.view.as_ref())
+    }
+
+    pub fn blob(&self, hash: &Hash) -> Option<Blob> {
+        if !self.media().contains(hash) {
+            return None
+        }
+        self.blobs().get(hash)
+    }
+
+    // Node-local too; values are `webhooks::Webhook` serialized as JSON.
+    pub fn webhooks(&self) -> MapIndex<&dyn Snapshot, Hash, String> {
+        MapIndex::new("ex_auction.local.webhooks", self.view.as_ref())
+    }
+
+    // Node-local copy of `lot_txs` replayed from the blocks up to `rebuilt_lot_txs_height`.
+    pub fn rebuilt_lot_txs(&self, lot_pub_key: &PublicKey) -> ListIndex<&dyn Snapshot, Hash> {
+        ListIndex::new_in_family("ex_auction.local.lot_txs", lot_pub_key, self.view.as_ref())
+    }
+
+    pub fn rebuilt_lot_txs_height(&self) -> Option<u64> {
+        Entry::new("ex_auction.local.lot_txs_height", self.view.as_ref()).get()
+    }
+
+    // Transactions touching a lot; the rebuilt copy wins for the blocks it covers.
+    pub fn indexed_lot_txs(&self, lot_pub_key: &PublicKey) -> Vec<Hash> {
+        let lot_txs = self.lot_txs(lot_pub_key);
+        let rebuilt_height = match self.rebuilt_lot_txs_height() {
+            Some(height) => height,
+            None => return lot_txs.iter().collect()
+        };
+        let core_schema = Schema::new(self.view.as_ref());
+        let locations = core_schema.transactions_locations();
+        let newer = lot_txs.iter()
+            .filter(|tx_hash| locations.get(tx_hash).map(|location| location.block_height().0 > rebuilt_height) == Some(true));
+        self.rebuilt_lot_txs(lot_pub_key).iter().chain(newer).collect()
+    }
+
+    // Node-local overrides of the node config; the value is `config::LocalSettings` serialized as JSON.
+    pub fn local_settings(&self) -> Option<String> {
+        Entry::new("ex_auction.local.settings", self.view.as_ref()).get()
+    }
 }
 
 
@@ -135,4 +629,197 @@ impl<'a> AuctionSchema<&'a mut Fork> {
     pub fn lot_bids_mut(&mut self, lot_public_key: &PublicKey) -> ListIndex<&mut Fork, PublicKey> {
         ListIndex::new_in_family("ex_auction.lot_bids", lot_public_key, &mut self.view)
     }
+
+    pub fn lot_bid_keys_mut(&mut self, lot_pub_key: &PublicKey) -> KeySetIndex<&mut Fork, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.lot_bid_keys", lot_pub_key, &mut self.view)
+    }
+
+    pub fn seller_lots_mut(&mut self, seller_pub_key: &PublicKey) -> KeySetIndex<&mut Fork, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.seller_lots", seller_pub_key, &mut self.view)
+    }
+
+    pub fn member_won_lots_mut(&mut self, member_id: u64) -> KeySetIndex<&mut Fork, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.member_won_lots", &member_id, &mut self.view)
+    }
+
+    pub fn member_bids_mut(&mut self, member_id: u64) -> KeySetIndex<&mut Fork, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.member_bids", &member_id, &mut self.view)
+    }
+
+    pub fn lot_txs_mut(&mut self, lot_pub_key: &PublicKey) -> ListIndex<&mut Fork, Hash> {
+        ListIndex::new_in_family("ex_auction.lot_txs", lot_pub_key, &mut self.view)
+    }
+
+    pub fn lot_history_mut(&mut self, pub_key: &PublicKey) -> ListIndex<&mut Fork, LotRecord> {
+        ListIndex::new_in_family("ex_auction.lot_history", pub_key, &mut self.view)
+    }
+
+    pub fn bid_history_mut(&mut self, pub_key: &PublicKey) -> ListIndex<&mut Fork, BidRecord> {
+        ListIndex::new_in_family("ex_auction.bid_history", pub_key, &mut self.view)
+    }
+
+    // Height of the block being executed, i.e. the one following the last committed block.
+    pub fn current_height(&self) -> u64 {
+        Schema::new(self.view.as_ref()).height().next().0
+    }
+
+    pub fn save_lot(&mut self, lot: Lot, tx_hash: &Hash) {
+        let height = self.current_height();
+        let pub_key = *lot.pub_key();
+        self.lot_history_mut(&pub_key).push(LotRecord::new(height, tx_hash, lot.clone()));
+        self.seller_lots_mut(lot.seller_pub_key()).insert(pub_key);
+        if !lot.opened() && lot.price() > 0 {
+            self.member_won_lots_mut(lot.winner_member_id()).insert(pub_key);
+        }
+        self.lots_mut().put(&pub_key, lot);
+    }
+
+    pub fn save_bid(&mut self, bid: Bid, tx_hash: &Hash) {
+        let height = self.current_height();
+        let pub_key = *bid.pub_key();
+        self.bid_history_mut(&pub_key).push(BidRecord::new(height, tx_hash, bid.clone()));
+        self.lot_bid_keys_mut(bid.lot_pub_key()).insert(pub_key);
+        if !bid.encrypted() {
+            self.member_bids_mut(bid.member_id()).insert(pub_key);
+        }
+        self.bids_mut().put(&pub_key, bid);
+    }
+
+    pub fn stats_mut(&mut self) -> Entry<&mut Fork, AuctionStats> {
+        Entry::new("ex_auction.stats", &mut self.view)
+    }
+
+    pub fn stats_history_mut(&mut self) -> MapIndex<&mut Fork, u64, AuctionStats> {
+        MapIndex::new("ex_auction.stats_history", &mut self.view)
+    }
+
+    pub fn lot_outcomes_mut(&mut self) -> ListIndex<&mut Fork, LotOutcome> {
+        ListIndex::new("ex_auction.lot_outcomes", &mut self.view)
+    }
+
+    pub fn update_stats<F: FnOnce(AuctionStats) -> AuctionStats>(&mut self, update: F) {
+        let stats = update(self.stats());
+        self.stats_mut().set(stats);
+    }
+
+    pub fn save_outcome(&mut self, lot: &Lot, price: u64, bids: u64) {
+        let outcome = LotOutcome::new(self.current_height(), lot.pub_key(), lot.seller_pub_key(), lot.price(), price, bids);
+        self.update_stats(|stats| stats.add_outcome(&outcome));
+        self.lot_outcomes_mut().push(outcome);
+    }
+
+    // Snapshots the counters once per block, so that windows can be computed as differences.
+    pub fn commit_stats(&mut self) {
+        let height = self.current_height();
+        let stats = self.stats();
+        self.stats_history_mut().put(&height, stats);
+    }
+
+    pub fn config_mut(&mut self) -> Entry<&mut Fork, String> {
+        Entry::new("ex_auction.config", &mut self.view)
+    }
+
+    pub fn set_config(&mut self, config: &ServiceConfig) {
+        self.config_mut().set(serde_json::to_string(config).unwrap());
+    }
+
+    pub fn lot_moderation_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, LotModeration> {
+        MapIndex::new("ex_auction.lot_moderation", &mut self.view)
+    }
+
+    pub fn member_bans_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, MemberBan> {
+        MapIndex::new("ex_auction.member_bans", &mut self.view)
+    }
+
+    pub fn reputations_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, Reputation> {
+        MapIndex::new("ex_auction.reputations", &mut self.view)
+    }
+
+    pub fn update_reputation<F: FnOnce(Reputation) -> Reputation>(&mut self, pub_key: &PublicKey, update: F) {
+        let reputation = update(self.reputation(pub_key));
+        self.reputations_mut().put(pub_key, reputation);
+    }
+
+    pub fn bid_members_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, PublicKey> {
+        MapIndex::new("ex_auction.bid_members", &mut self.view)
+    }
+
+    pub fn lot_winners_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, PublicKey> {
+        MapIndex::new("ex_auction.lot_winners", &mut self.view)
+    }
+
+    pub fn settlements_mut(&mut self) -> MapIndex<&mut Fork, PublicKey, Settlement> {
+        MapIndex::new("ex_auction.settlements", &mut self.view)
+    }
+
+    pub fn lot_ratings_mut(&mut self, lot_pub_key: &PublicKey) -> MapIndex<&mut Fork, PublicKey, Rating> {
+        MapIndex::new_in_family("ex_auction.lot_ratings", lot_pub_key, &mut self.view)
+    }
+
+    pub fn member_ratings_mut(&mut self, pub_key: &PublicKey) -> ListIndex<&mut Fork, Rating> {
+        ListIndex::new_in_family("ex_auction.member_ratings", pub_key, &mut self.view)
+    }
+
+    pub fn config_proposals_mut(&mut self) -> MapIndex<&mut Fork, Hash, ConfigProposal> {
+        MapIndex::new("ex_auction.config_proposals", &mut self.view)
+    }
+
+    pub fn config_votes_mut(&mut self, proposal_hash: &Hash) -> KeySetIndex<&mut Fork, PublicKey> {
+        KeySetIndex::new_in_family("ex_auction.config_votes", proposal_hash, &mut self.view)
+    }
+
+    pub fn pending_config_mut(&mut self) -> Entry<&mut Fork, Hash> {
+        Entry::new("ex_auction.pending_config", &mut self.view)
+    }
+
+    pub fn config_history_mut(&mut self) -> ListIndex<&mut Fork, ConfigRecord> {
+        ListIndex::new("ex_auction.config_history", &mut self.view)
+    }
+
+    pub fn init_config(&mut self, config: &ServiceConfig) {
+        self.set_config(config);
+        self.config_history_mut().push(ConfigRecord::new(0, &Hash::zero(), &serde_json::to_string(config).unwrap()));
+    }
+
+    // Runs before every block is committed. A config due at the next height is activated now,
+    // so that every transaction of that block already runs under it.
+    pub fn activate_pending_config(&mut self) {
+        let height = self.current_height() + 1;
+        let proposal_hash = match self.pending_config() {
+            Some(proposal_hash) => proposal_hash,
+            None => return
+        };
+        let proposal = self.config_proposals().get(&proposal_hash).unwrap();
+        if proposal.actual_from() > height {
+            return
+        }
+        let config: ServiceConfig = serde_json::from_str(proposal.config()).unwrap();
+        self.set_config(&config);
+        self.config_history_mut().push(ConfigRecord::new(height, &proposal_hash, proposal.config()));
+        self.pending_config_mut().remove();
+    }
+
+    pub fn media_mut(&mut self) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new("ex_auction.media", &mut self.view)
+    }
+
+    pub fn blobs_mut(&mut self) -> MapIndex<&mut Fork, Hash, Blob> {
+        MapIndex::new("ex_auction.local.blobs", &mut self.view)
+    }
+
+    pub fn webhooks_mut(&mut self) -> MapIndex<&mut Fork, Hash, String> {
+        MapIndex::new("ex_auction.local.webhooks", &mut self.view)
+    }
+
+    pub fn rebuilt_lot_txs_mut(&mut self, lot_pub_key: &PublicKey) -> ListIndex<&mut Fork, Hash> {
+        ListIndex::new_in_family("ex_auction.local.lot_txs", lot_pub_key, &mut self.view)
+    }
+
+    pub fn rebuilt_lot_txs_height_mut(&mut self) -> Entry<&mut Fork, u64> {
+        Entry::new("ex_auction.local.lot_txs_height", &mut self.view)
+    }
+
+    pub fn local_settings_mut(&mut self) -> Entry<&mut Fork, String> {
+        Entry::new("ex_auction.local.settings", &mut self.view)
+    }
 }
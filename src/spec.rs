@@ -0,0 +1,680 @@
+use std::collections::{BTreeMap, HashMap};
+use serde_json::{Map, Value};
+use exonum::crypto::{Hash, PublicKey, SecretKey, Signature};
+use exonum::messages::ServiceMessage;
+use juniper::http::GraphQLRequest;
+use api::{
+    ApiContext, API_VERSIONS, LotQuery, BidQuery, LotBidsQuery, LotHistoryQuery, LotModerationQuery, SettlementQuery, ReputationQuery,
+    RatingsQuery, MemberRatings, MemberReputation, TransactionResponse, DryRunResponse, TxStatus, TxStatusQuery, TxState,
+    TransactionStatus, LotTxsQuery, LotTransaction, BatchQuery, BatchResponse, LotsQuery, StatsQuery, SellerVolume, StatsReport, MediaQuery,
+    Media, MediaResponse, WebhookQuery, WebhookEntry, RedeliverQuery, RedeliverReport, PendingTransaction, SchemaStats, RebuildReport, ConfigProposalInfo,
+    ConfigChange, NodeSettings
+};
+use schema::{
+    Lot, Bid, LotRecord, LotModeration, MemberBan, Reputation, Settlement, Rating, ServiceConfig, MAX_RATING, MAX_REVIEW_LENGTH
+};
+use transactions::{
+    AuctionTransactions, TxCreateLot, TxCreateBid, TxCloseLot, TxProposeConfig, TxVoteConfig, TxSuspendLot, TxDelistLot, TxBanMember,
+    TxSettleLot, TxRateCounterparty
+};
+use consistency::{ConsistencyReport, Inconsistency};
+use errors::ErrorInfo;
+use events::{AuctionEvent, EventFilter};
+use webhooks::{Webhook, RetryPolicy};
+use media::MediaConfig;
+use config::LocalSettings;
+use routes::{ApiScope, RouteDoc, VersionInfo};
+use {AUCTION_SERVICE_ID, AUCTION_SERVICE_NAME};
+
+
+pub const OPENAPI_VERSION: &str = "3.0.0";
+
+
+// Component schemas collected while documenting the routes.
+#[derive(Debug, Default)]
+pub struct Components {
+    schemas: Map<String, Value>
+}
+
+
+impl Components {
+    // Named types are defined on first use and referred to by `$ref` everywhere else.
+    pub fn define<F>(&mut self, name: &str, schema: F) -> Value
+    where
+        F: FnOnce(&mut Components) -> Value
+    {
+        if !self.schemas.contains_key(name) {
+            self.schemas.insert(name.to_owned(), Value::Null);
+            let schema = schema(self);
+            self.schemas.insert(name.to_owned(), schema);
+        }
+        reference(name)
+    }
+}
+
+
+// How a type looks in API JSON and query strings.
+pub trait ApiSchema {
+    fn schema(components: &mut Components) -> Value;
+
+    // Whether a field of this type may be left out.
+    fn optional() -> bool {
+        false
+    }
+}
+
+
+// How a field of an `encoding_struct!` or a transaction looks; exonum writes those with its
+// own serializer, which turns 64-bit integers into strings.
+pub trait FieldSchema {
+    fn field_schema(components: &mut Components) -> Value;
+}
+
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn nullable(schema: Value) -> Value {
+    if schema.get("$ref").is_some() {
+        json!({ "allOf": [schema], "nullable": true })
+    } else {
+        let mut schema = schema;
+        schema["nullable"] = json!(true);
+        schema
+    }
+}
+
+fn object(properties: Map<String, Value>, required: Vec<&str>) -> Value {
+    json!({ "type": "object", "required": required, "properties": properties })
+}
+
+fn hex() -> Value {
+    reference("Hex")
+}
+
+// Matches serde's `rename_all = "snake_case"` for variant names.
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            result.push('_');
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+macro_rules! schema_or {
+    (; $default:expr) => ($default);
+    ($schema:expr; $default:expr) => ($schema);
+}
+
+
+macro_rules! primitive_schema {
+    ($($ty:ty => $schema:expr),* $(,)*) => {
+        $(
+            impl ApiSchema for $ty {
+                fn schema(_components: &mut Components) -> Value {
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+primitive_schema! {
+    bool => json!({ "type": "boolean" }),
+    u8 => json!({ "type": "integer", "minimum": 0, "maximum": 255 }),
+    u32 => json!({ "type": "integer", "minimum": 0 }),
+    u64 => json!({ "type": "integer", "format": "int64", "minimum": 0 }),
+    usize => json!({ "type": "integer", "format": "int64", "minimum": 0 }),
+    i64 => json!({ "type": "integer", "format": "int64" }),
+    f64 => json!({ "type": "number" }),
+    String => json!({ "type": "string" }),
+    Value => json!({}),
+    // Routes without a query or a body.
+    () => Value::Null,
+}
+
+impl ApiSchema for PublicKey {
+    fn schema(components: &mut Components) -> Value {
+        components.define("PublicKey", |_| json!({ "type": "string", "pattern": "^[0-9a-f]{64}$" }))
+    }
+}
+
+impl ApiSchema for Hash {
+    fn schema(components: &mut Components) -> Value {
+        components.define("Hash", |_| json!({ "type": "string", "pattern": "^[0-9a-f]{64}$" }))
+    }
+}
+
+impl ApiSchema for Signature {
+    fn schema(components: &mut Components) -> Value {
+        components.define("Signature", |_| json!({ "type": "string", "pattern": "^[0-9a-f]{128}$" }))
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    fn schema(components: &mut Components) -> Value {
+        nullable(T::schema(components))
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "array", "items": T::schema(components) })
+    }
+}
+
+impl<K, V: ApiSchema> ApiSchema for BTreeMap<K, V> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "object", "additionalProperties": V::schema(components) })
+    }
+}
+
+impl<K, V: ApiSchema> ApiSchema for HashMap<K, V> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "object", "additionalProperties": V::schema(components) })
+    }
+}
+
+// Foreign type with private fields, so it is described by hand.
+impl ApiSchema for GraphQLRequest {
+    fn schema(components: &mut Components) -> Value {
+        components.define("GraphQLRequest", |_| json!({
+            "type": "object",
+            "required": ["query"],
+            "properties": {
+                "query": { "type": "string" },
+                "operationName": { "type": "string", "nullable": true },
+                "variables": { "type": "object", "nullable": true }
+            }
+        }))
+    }
+}
+
+
+macro_rules! field_schema {
+    ($($ty:ty => $schema:expr),* $(,)*) => {
+        $(
+            impl<'a> FieldSchema for $ty {
+                fn field_schema(components: &mut Components) -> Value {
+                    $schema(components)
+                }
+            }
+        )*
+    };
+}
+
+field_schema! {
+    bool => bool::schema,
+    u8 => u8::schema,
+    u64 => |_: &mut Components| reference("U64String"),
+    &'a str => String::schema,
+    &'a PublicKey => PublicKey::schema,
+    &'a Hash => Hash::schema,
+    &'a Signature => Signature::schema,
+    &'a [Hash] => Vec::<Hash>::schema,
+    Vec<u8> => Vec::<u8>::schema,
+}
+
+
+// Documents a serde struct. Destructuring it stops compiling once the listed fields and the
+// declaration drift apart; `= schema` overrides what the field type gives. Fields of `Option`
+// types and fields marked `#[serde(default)]` are not required.
+macro_rules! struct_schema {
+    ($(#[$attr:meta])* $name:ident $(<$param:ident>)* { $($(#[$field_attr:meta])* $field:ident: $ty:ty $(= $schema:expr)*),* $(,)* }) => {
+        impl $(<$param: ApiSchema>)* ApiSchema for $name $(<$param>)* {
+            fn schema(components: &mut Components) -> Value {
+                #[allow(dead_code)]
+                fn fields $(<$param>)* (value: $name $(<$param>)*) {
+                    let $name { $($field),* } = value;
+                    $(let _: $ty = $field;)*
+                }
+                let all_default = stringify!($(#[$attr])*).contains("default");
+                let define = |components: &mut Components| {
+                    let mut properties = Map::new();
+                    let mut required = Vec::new();
+                    $(
+                        properties.insert(stringify!($field).to_owned(), schema_or!($($schema)*; <$ty as ApiSchema>::schema(components)));
+                        if !all_default && !<$ty as ApiSchema>::optional() && !stringify!($(#[$field_attr])*).contains("default") {
+                            required.push(stringify!($field));
+                        }
+                    )*
+                    object(properties, required)
+                };
+                let generic: &[&str] = &[$(stringify!($param)),*];
+                if generic.is_empty() {
+                    components.define(stringify!($name), define)
+                } else {
+                    define(components)
+                }
+            }
+        }
+    };
+}
+
+
+// Documents an `encoding_struct!`. Building it through `new` and naming every getter stops
+// compiling once the listed fields and the declaration drift apart.
+macro_rules! encoding_schema {
+    ($name:ident { $($field:ident: $ty:ty $(= $schema:expr)*),* $(,)* }) => {
+        impl ApiSchema for $name {
+            fn schema(components: &mut Components) -> Value {
+                #[allow(dead_code, clippy::too_many_arguments)]
+                fn fields($($field: $ty),*) -> $name {
+                    $(let _ = $name::$field;)*
+                    $name::new($($field),*)
+                }
+                components.define(stringify!($name), |components| {
+                    let mut properties = Map::new();
+                    $(properties.insert(stringify!($field).to_owned(), schema_or!($($schema)*; <$ty as FieldSchema>::field_schema(components)));)*
+                    object(properties, vec![$(stringify!($field)),*])
+                })
+            }
+        }
+
+        impl FieldSchema for $name {
+            fn field_schema(components: &mut Components) -> Value {
+                <$name as ApiSchema>::schema(components)
+            }
+        }
+    };
+}
+
+
+// Documents the transaction set the same way as `encoding_schema!`, with the exonum message
+// envelope around every body. The `match` stops compiling when a transaction is left out.
+macro_rules! transaction_schemas {
+    ($set:ident { $($name:ident { $($field:ident: $ty:ty $(= $schema:expr)*),* $(,)* })* }) => {
+        $(
+            impl ApiSchema for $name {
+                fn schema(components: &mut Components) -> Value {
+                    #[allow(dead_code, clippy::too_many_arguments)]
+                    fn fields($($field: $ty,)* secret_key: &SecretKey) -> $name {
+                        $(let _ = $name::$field;)*
+                        $name::new($($field,)* secret_key)
+                    }
+                    components.define(stringify!($name), |components| {
+                        let mut properties = Map::new();
+                        $(properties.insert(stringify!($field).to_owned(), schema_or!($($schema)*; <$ty as FieldSchema>::field_schema(components)));)*
+                        let body = object(properties, vec![$(stringify!($field)),*]);
+                        json!({
+                            "type": "object",
+                            "required": ["body", "signature", "message_id", "service_id", "protocol_version"],
+                            "properties": {
+                                "body": body,
+                                "signature": Signature::schema(components),
+                                "message_id": { "type": "integer", "enum": [<$name as ServiceMessage>::MESSAGE_ID] },
+                                "service_id": { "type": "integer", "enum": [AUCTION_SERVICE_ID] },
+                                "protocol_version": { "type": "integer" }
+                            }
+                        })
+                    })
+                }
+            }
+        )*
+
+        impl ApiSchema for $set {
+            fn schema(components: &mut Components) -> Value {
+                #[allow(dead_code)]
+                fn variants(tx: $set) {
+                    match tx {
+                        $($set::$name(_) => ()),*
+                    }
+                }
+                components.define(stringify!($set), |components| json!({ "oneOf": [$(<$name as ApiSchema>::schema(components)),*] }))
+            }
+        }
+    };
+}
+
+
+// Documents an internally tagged enum with `rename_all = "snake_case"`; the `match` stops
+// compiling once the listed variants and the declaration drift apart.
+macro_rules! tagged_schema {
+    ($name:ident, $tag:expr, { $($variant:ident { $($field:ident: $ty:ty),* $(,)* }),* $(,)* }) => {
+        impl ApiSchema for $name {
+            fn schema(components: &mut Components) -> Value {
+                #[allow(dead_code)]
+                fn variants(value: $name) {
+                    match value {
+                        $($name::$variant { $($field),* } => { $(let _: $ty = $field;)* }),*
+                    }
+                }
+                components.define(stringify!($name), |components| {
+                    let variants: Vec<Value> = vec![$({
+                        let mut properties = Map::new();
+                        properties.insert($tag.to_owned(), json!({ "type": "string", "enum": [snake_case(stringify!($variant))] }));
+                        #[allow(unused_mut)]
+                        let mut required = vec![$tag];
+                        $(
+                            properties.insert(stringify!($field).to_owned(), <$ty as ApiSchema>::schema(components));
+                            if !<$ty as ApiSchema>::optional() {
+                                required.push(stringify!($field));
+                            }
+                        )*
+                        object(properties, required)
+                    }),*];
+                    json!({ "oneOf": variants })
+                })
+            }
+        }
+    };
+}
+
+
+// Documents a fieldless enum with `rename_all = "snake_case"`.
+macro_rules! unit_schema {
+    ($name:ident { $($variant:ident),* $(,)* }) => {
+        impl ApiSchema for $name {
+            fn schema(components: &mut Components) -> Value {
+                #[allow(dead_code)]
+                fn variants(value: $name) {
+                    match value {
+                        $($name::$variant => ()),*
+                    }
+                }
+                components.define(stringify!($name), |_| {
+                    json!({ "type": "string", "enum": [$(snake_case(stringify!($variant))),*] })
+                })
+            }
+        }
+    };
+}
+
+
+struct_schema!(LotQuery { pub_key: PublicKey, height: Option<u64> });
+struct_schema!(BidQuery { pub_key: PublicKey, height: Option<u64> });
+struct_schema!(LotBidsQuery { lot_pub_key: PublicKey, height: Option<u64> });
+struct_schema!(LotHistoryQuery { pub_key: PublicKey });
+struct_schema!(LotTxsQuery { lot_pub_key: PublicKey });
+struct_schema!(LotModerationQuery { lot_pub_key: PublicKey });
+struct_schema!(SettlementQuery { lot_pub_key: PublicKey });
+struct_schema!(ReputationQuery { pub_key: PublicKey });
+struct_schema!(RatingsQuery { pub_key: PublicKey });
+struct_schema!(TxStatusQuery { tx_hash: Hash });
+struct_schema!(StatsQuery { from: Option<u64>, to: Option<u64> });
+struct_schema!(MediaQuery { hash: Hash });
+struct_schema!(WebhookQuery { id: Hash });
+struct_schema!(BatchQuery {
+    pub_keys: Vec<PublicKey>,
+    height: Option<u64>,
+    #[serde(default)]
+    include_delisted: bool = json!({ "type": "boolean", "description": "Delisted lots are looked up like any other when set" })
+});
+struct_schema!(BatchResponse<T> {
+    found: Vec<T>,
+    missing: Vec<PublicKey>,
+    #[serde(default)]
+    delisted: Vec<PublicKey> = json!({
+        "type": "array",
+        "items": reference("PublicKey"),
+        "description": "Keys of delisted lots left out because `include_delisted` is not set"
+    })
+});
+struct_schema!(LotsQuery {
+    seller_pub_key: Option<PublicKey>,
+    after: Option<PublicKey> = json!({ "allOf": [reference("PublicKey")], "description": "Key of the last lot of the previous page" }),
+    limit: Option<u64> = json!({ "type": "integer", "description": "Page size, 20 by default and at most 100" }),
+    #[serde(default)]
+    include_delisted: bool
+});
+
+struct_schema!(TransactionResponse { tx_hash: Hash });
+struct_schema!(DryRunResponse { tx_hash: Hash, success: bool, error: Option<ErrorInfo> });
+struct_schema!(TransactionStatus { tx_hash: Hash, status: TxState, lot_pub_key: Option<PublicKey>, height: Option<u64>, error: Option<ErrorInfo> });
+struct_schema!(LotTransaction { tx_hash: Hash, height: u64, position: u64, status: TxStatus, content: AuctionTransactions });
+struct_schema!(PendingTransaction { tx_hash: Hash, content: AuctionTransactions });
+struct_schema!(SellerVolume { seller_pub_key: PublicKey, lots: u64, volume: u64 });
+struct_schema!(StatsReport {
+    from: u64,
+    to: u64,
+    open_lots: u64,
+    closed_lots: u64,
+    lots_created: u64,
+    lots_closed: u64,
+    lots_sold: u64,
+    bids: u64,
+    volume: u64,
+    average_price: f64,
+    average_reserve: f64,
+    bids_per_lot: BTreeMap<u64, u64>,
+    top_sellers: Vec<SellerVolume>
+});
+struct_schema!(Media { mime_type: String, data: String = hex() });
+struct_schema!(MediaResponse { hash: Hash });
+struct_schema!(MemberReputation {
+    pub_key: PublicKey,
+    score: i64 = json!({ "type": "integer", "format": "int64", "description": "Completed deals minus twice the failed ones" }),
+    reputation: Reputation,
+    ban: Option<MemberBan>
+});
+struct_schema!(MemberRatings { pub_key: PublicKey, count: u64, average: Option<f64>, ratings: Vec<Rating> });
+struct_schema!(#[serde(default)] ServiceConfig {
+    admin_keys: Vec<PublicKey>,
+    listing_fee: u64,
+    sale_fee_bp: u64,
+    min_bid_increment: u64,
+    max_description_length: u64,
+    default_deadline: u64,
+    vote_threshold: u64
+});
+struct_schema!(ConfigProposalInfo {
+    proposal_hash: Hash,
+    proposer: PublicKey,
+    config: ServiceConfig,
+    actual_from: u64,
+    votes: Vec<PublicKey>,
+    required_votes: u64,
+    accepted: bool
+});
+struct_schema!(ConfigChange { height: u64, proposal_hash: Hash, config: ServiceConfig });
+struct_schema!(ErrorInfo { code: u8, message: String, context: BTreeMap<String, Value> });
+struct_schema!(ConsistencyReport { lots_checked: u64, bids_checked: u64, inconsistencies: Vec<Inconsistency> });
+struct_schema!(EventFilter { lot_pub_key: Option<PublicKey>, seller_pub_key: Option<PublicKey> });
+struct_schema!(Webhook {
+    url: String = json!({ "type": "string", "format": "uri" }),
+    secret: String = json!({
+        "type": "string",
+        "description": "Key of the HMAC-SHA256 sent in the X-Auction-Signature header; listed as <redacted>"
+    }),
+    #[serde(default)]
+    filter: EventFilter,
+    #[serde(default)]
+    events: Vec<String> = json!({ "type": "array", "items": { "type": "string", "enum": ["lot_created", "bid_placed", "lot_closed", "lot_settled"] } })
+});
+struct_schema!(WebhookEntry { id: Hash, webhook: Webhook });
+struct_schema!(RedeliverQuery { from_height: u64, to_height: u64 });
+struct_schema!(RedeliverReport { events: u64, queued: u64 });
+struct_schema!(SchemaStats { lots: u64, bids: u64, lot_txs: u64, lot_outcomes: u64, stats_snapshots: u64, media: u64, blobs: u64, webhooks: u64 });
+struct_schema!(RebuildReport { lot_txs: u64 });
+struct_schema!(MediaConfig { max_size: usize, mime_types: Vec<String> });
+struct_schema!(RetryPolicy { attempts: u32, backoff_ms: u64 });
+struct_schema!(LocalSettings {
+    #[serde(default)]
+    media: MediaConfig,
+    #[serde(default)]
+    webhook_retry: RetryPolicy
+});
+struct_schema!(NodeSettings { media: MediaConfig, webhook_retry: RetryPolicy, webhooks: usize, overridden: bool });
+struct_schema!(VersionInfo { name: String, deprecated: bool, routes: Vec<String>, legacy_aliases: Vec<String> });
+
+tagged_schema!(TxStatus, "type", {
+    Success {},
+    Error { code: u8, description: Option<String> },
+    Panic { description: Option<String> }
+});
+tagged_schema!(AuctionEvent, "type", {
+    LotCreated { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey },
+    BidPlaced { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey, bid_pub_key: PublicKey },
+    LotClosed { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey, price: u64, winner_member_id: u64, bids: u64 },
+    LotSettled { height: u64, tx_hash: Hash, lot_pub_key: PublicKey, seller_pub_key: PublicKey, winner: PublicKey, outcome: u8 }
+});
+tagged_schema!(Inconsistency, "kind", {
+    DanglingBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    MisplacedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    OrphanedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    UnlistedBid { lot_pub_key: PublicKey, bid_pub_key: PublicKey },
+    OrphanedLot { lot_pub_key: PublicKey }
+});
+unit_schema!(TxState { Pending, Committed, Failed });
+
+encoding_schema!(Lot {
+    pub_key: &PublicKey,
+    seller_pub_key: &PublicKey,
+    pub_session_key: &PublicKey,
+    token_hash: &Hash,
+    media_hashes: &[Hash],
+    description: &str,
+    price: u64,
+    opened: bool,
+    winner_member_id: u64,
+    min_reputation: u64
+});
+encoding_schema!(Bid { pub_key: &PublicKey, lot_pub_key: &PublicKey, member_id: u64, sum: u64, encrypted: bool, data: Vec<u8> });
+encoding_schema!(LotRecord { height: u64, tx_hash: &Hash, lot: Lot });
+encoding_schema!(LotModeration { suspended: bool, delisted: bool, reason: &str, admin: &PublicKey, height: u64 });
+encoding_schema!(MemberBan { banned: bool, reason: &str, admin: &PublicKey, height: u64 });
+encoding_schema!(Reputation { sales: u64, purchases: u64, non_payments: u64, failed_deliveries: u64 });
+encoding_schema!(Settlement {
+    winner: &PublicKey,
+    outcome: u8 = json!({ "type": "integer", "enum": [0, 1, 2], "description": "0 completed, 1 non-payment, 2 failed delivery" }),
+    admin: &PublicKey,
+    height: u64
+});
+encoding_schema!(Rating {
+    lot_pub_key: &PublicKey,
+    from: &PublicKey,
+    to: &PublicKey,
+    rating: u8 = json!({ "type": "integer", "minimum": 1, "maximum": MAX_RATING }),
+    review: &str,
+    height: u64
+});
+
+transaction_schemas!(AuctionTransactions {
+    TxCreateLot {
+        pub_key: &PublicKey,
+        seller_pub_key: &PublicKey,
+        seller_signature: &Signature = json!({ "allOf": [reference("Signature")], "description": "Seller's signature of pub_key; the transaction is signed by the lot key" }),
+        pub_session_key: &PublicKey,
+        token_hash: &Hash,
+        media_hashes: &[Hash],
+        description: &str,
+        price: u64,
+        min_reputation: u64
+    }
+    TxCreateBid {
+        pub_key: &PublicKey,
+        lot_pub_key: &PublicKey,
+        member_pub_key: &PublicKey,
+        bid_signature: &Signature = json!({ "allOf": [reference("Signature")], "description": "Bid key's signature of member_pub_key; the transaction is signed by the member key" }),
+        data: Vec<u8>
+    }
+    TxCloseLot { lot_pub_key: &PublicKey, pub_session_key: &PublicKey, secret_session_key: &str = hex() }
+    TxProposeConfig { from: &PublicKey, config: &str = json!({ "type": "string", "description": "ServiceConfig as JSON" }), actual_from: u64 }
+    TxVoteConfig { from: &PublicKey, proposal_hash: &Hash }
+    TxSuspendLot { from: &PublicKey, lot_pub_key: &PublicKey, suspended: bool, reason: &str }
+    TxDelistLot { from: &PublicKey, lot_pub_key: &PublicKey, reason: &str }
+    TxBanMember { from: &PublicKey, member_pub_key: &PublicKey, banned: bool, reason: &str }
+    TxSettleLot {
+        from: &PublicKey,
+        lot_pub_key: &PublicKey,
+        outcome: u8 = json!({ "type": "integer", "enum": [0, 1, 2], "description": "0 completed, 1 non-payment, 2 failed delivery" })
+    }
+    TxRateCounterparty {
+        from: &PublicKey,
+        lot_pub_key: &PublicKey,
+        rating: u8 = json!({ "type": "integer", "minimum": 1, "maximum": MAX_RATING }),
+        review: &str = json!({ "type": "string", "maxLength": MAX_REVIEW_LENGTH })
+    }
+});
+
+
+fn responses(schema: Value) -> Value {
+    json!({
+        "200": { "description": "Success", "content": { "application/json": { "schema": schema } } },
+        "400": { "description": "Invalid request", "content": { "application/json": { "schema": reference("ErrorInfo") } } },
+        "404": { "description": "Not found", "content": { "application/json": { "schema": reference("ErrorInfo") } } },
+        "500": { "description": "Internal error", "content": { "application/json": { "schema": reference("ErrorInfo") } } }
+    })
+}
+
+fn document(method: &str, scope: ApiScope, doc: &RouteDoc, components: &mut Components) -> Value {
+    let summary = match scope {
+        ApiScope::Public => doc.summary.to_owned(),
+        ApiScope::Private => format!("{}, served on the private API only", doc.summary)
+    };
+    let mut operation = json!({ "tags": [doc.tag], "summary": summary });
+    let query = (doc.query)(components);
+    let response = (doc.response)(components);
+    if !query.is_null() {
+        if method == "get" {
+            operation["parameters"] = json!([{ "name": "query", "in": "query", "required": true, "style": "form", "explode": true, "schema": query }]);
+        } else {
+            operation["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": query } } });
+        }
+    }
+    operation["responses"] = if doc.stream {
+        json!({
+            "101": { "description": "Switching to the WebSocket protocol; every text frame is a message", "x-message": response },
+            "400": { "description": "Invalid request", "content": { "application/json": { "schema": reference("ErrorInfo") } } }
+        })
+    } else {
+        responses(response)
+    };
+    operation
+}
+
+
+// Built from the same route tables `AuctionApi::wire` mounts, so every served path is documented.
+pub fn openapi() -> Value {
+    let mut components = Components::default();
+    components.define("U64String", |_| json!({ "type": "string", "pattern": "^[0-9]+$", "description": "Unsigned 64-bit integer encoded as a string" }));
+    components.define("Hex", |_| json!({ "type": "string", "pattern": "^([0-9a-f]{2})*$" }));
+    ErrorInfo::schema(&mut components);
+
+    let mut paths = Map::new();
+    let versions = RouteDoc {
+        tag: "versions",
+        summary: "List the API versions with their public routes and legacy aliases",
+        query: <()>::schema,
+        response: Vec::<VersionInfo>::schema,
+        stream: false
+    };
+    paths.insert("/versions".to_owned(), json!({ "get": document("get", ApiScope::Public, &versions, &mut components) }));
+    let context = ApiContext::default();
+    for version in API_VERSIONS {
+        for route in (version.routes)(&context).routes() {
+            let method = route.handler.method.as_str().to_lowercase();
+            let mut operation = document(&method, route.scope, &route.doc, &mut components);
+            if version.deprecated {
+                operation["deprecated"] = json!(true);
+            }
+            let path = paths.entry(format!("/{}/{}", version.name, route.handler.name)).or_insert_with(|| json!({}));
+            path[&method] = operation.clone();
+            if version.legacy_aliases.contains(&route.handler.name.as_str()) {
+                operation["deprecated"] = json!(true);
+                operation["summary"] = json!(format!("Legacy alias of /{}/{}", version.name, route.handler.name));
+                let alias = paths.entry(format!("/{}", route.handler.name)).or_insert_with(|| json!({}));
+                alias[&method] = operation;
+            }
+        }
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": AUCTION_SERVICE_NAME,
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": format!("/api/services/{}", AUCTION_SERVICE_NAME) }],
+        "paths": paths,
+        "components": { "schemas": components.schemas }
+    })
+}
@@ -1,73 +1,349 @@
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
 use exonum::{
-    blockchain::{ExecutionError, ExecutionResult, Transaction}, crypto::{Hash, PublicKey, SecretKey},
-    messages::Message, storage::Fork,
+    blockchain::{ExecutionResult, Schema, Transaction, TransactionError, TransactionSet},
+    crypto::{self, CryptoHash, Hash, PublicKey, SecretKey, Signature}, encoding::serialize::FromHex, helpers::Height,
+    messages::Message, storage::{Fork, Snapshot},
 };
-use schema::{AuctionSchema, Lot, Bid};
-use errors::Error;
+use serde_json;
+use schema::{
+    AuctionSchema, Lot, Bid, ConfigProposal, LotModeration, MemberBan, Rating, ServiceConfig, Settlement, SettlementOutcome,
+    MAX_RATING, MAX_REVIEW_LENGTH
+};
+use errors::{Error, ErrorInfo};
 
 use AUCTION_SERVICE_ID;
 
 
-transactions! {
-    pub AuctionTransactions {
-        const SERVICE_ID = AUCTION_SERVICE_ID;
+// The generated constructors take every field plus the signing key, which is more than clippy
+// allows; the module keeps the allowance off the hand-written code.
+mod messages {
+    #![allow(clippy::too_many_arguments)]
+    use exonum::crypto::{Hash, PublicKey, Signature};
+
+    use AUCTION_SERVICE_ID;
+
+    transactions! {
+        pub AuctionTransactions {
+            const SERVICE_ID = AUCTION_SERVICE_ID;
+
+            struct TxCreateLot {
+                pub_key: &PublicKey,
+                seller_pub_key: &PublicKey,
+                seller_signature: &Signature,
+                pub_session_key: &PublicKey,
+                token_hash: &Hash,
+                media_hashes: &[Hash],
+                description: &str,
+                price: u64,
+                min_reputation: u64
+            }
+
+            struct TxCreateBid {
+                pub_key: &PublicKey,
+                lot_pub_key: &PublicKey,
+                member_pub_key: &PublicKey,
+                bid_signature: &Signature,
+                data: Vec<u8>
+            }
+
+            struct TxCloseLot {
+                lot_pub_key: &PublicKey,
+                pub_session_key: &PublicKey,
+                secret_session_key: &str
+            }
+
+            struct TxProposeConfig {
+                from: &PublicKey,
+                config: &str,
+                actual_from: u64
+            }
+
+            struct TxVoteConfig {
+                from: &PublicKey,
+                proposal_hash: &Hash
+            }
 
-        struct TxCreateLot {
-            pub_key: &PublicKey,
-            pub_session_key: &PublicKey,
-            token_hash: &Hash,
-            description: &str,
-            price: u64
+            struct TxSuspendLot {
+                from: &PublicKey,
+                lot_pub_key: &PublicKey,
+                suspended: bool,
+                reason: &str
+            }
+
+            struct TxDelistLot {
+                from: &PublicKey,
+                lot_pub_key: &PublicKey,
+                reason: &str
+            }
+
+            struct TxBanMember {
+                from: &PublicKey,
+                member_pub_key: &PublicKey,
+                banned: bool,
+                reason: &str
+            }
+
+            struct TxSettleLot {
+                from: &PublicKey,
+                lot_pub_key: &PublicKey,
+                outcome: u8
+            }
+
+            struct TxRateCounterparty {
+                from: &PublicKey,
+                lot_pub_key: &PublicKey,
+                rating: u8,
+                review: &str
+            }
         }
+    }
+}
+pub use self::messages::*;
+
 
-        struct TxCreateBid {
-            pub_key: &PublicKey,
-            lot_pub_key: &PublicKey,
-            data: Vec<u8>
+impl AuctionTransactions {
+    // `None` for the transactions that touch no lot.
+    pub fn lot_pub_key(&self) -> Option<&PublicKey> {
+        match *self {
+            AuctionTransactions::TxCreateLot(ref tx) => Some(tx.pub_key()),
+            AuctionTransactions::TxCreateBid(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxCloseLot(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxSuspendLot(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxDelistLot(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxSettleLot(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxRateCounterparty(ref tx) => Some(tx.lot_pub_key()),
+            AuctionTransactions::TxProposeConfig(_) | AuctionTransactions::TxVoteConfig(_) | AuctionTransactions::TxBanMember(_) => None
         }
+    }
+
+    // Payload failures that no state can fix; execution runs the same checks first.
+    pub fn check_payload(&self) -> Result<(), ErrorInfo> {
+        match *self {
+            AuctionTransactions::TxCreateBid(ref tx) => tx.check_payload(),
+            AuctionTransactions::TxSettleLot(ref tx) => tx.check_payload(),
+            AuctionTransactions::TxRateCounterparty(ref tx) => tx.check_payload(),
+            AuctionTransactions::TxCreateLot(_) | AuctionTransactions::TxCloseLot(_) | AuctionTransactions::TxProposeConfig(_)
+                | AuctionTransactions::TxVoteConfig(_) | AuctionTransactions::TxSuspendLot(_) | AuctionTransactions::TxDelistLot(_)
+                | AuctionTransactions::TxBanMember(_) => Ok(())
+        }
+    }
+}
+
+
+fn block_lot_txs(fork: &Fork, height: Height) -> Vec<(Hash, AuctionTransactions)> {
+    let schema = Schema::new(fork);
+    let transactions = schema.transactions();
+    schema.block_transactions(height).iter()
+        .filter_map(|tx_hash| transactions.get(&tx_hash).map(|raw| (tx_hash, raw)))
+        .filter(|(_, raw)| raw.service_id() == AUCTION_SERVICE_ID)
+        .filter_map(|(tx_hash, raw)| AuctionTransactions::tx_from_raw(raw).ok().map(|tx| (tx_hash, tx)))
+        .collect()
+}
+
+
+// Called before the block is committed, so failed transactions get into `lot_txs` too.
+pub fn index_lot_txs(fork: &mut Fork) {
+    let height = Schema::new(&*fork).height().next();
+    let txs = block_lot_txs(fork, height);
+    let mut schema = AuctionSchema::new(fork);
+    for (tx_hash, tx) in txs {
+        if let Some(lot_pub_key) = tx.lot_pub_key() {
+            schema.lot_txs_mut(lot_pub_key).push(tx_hash);
+        }
+    }
+}
+
 
-        struct TxCloseLot {
-            lot_pub_key: &PublicKey,
-            pub_session_key: &PublicKey,
-            secret_session_key: &str
+// Replays every committed block into the node-local copy of `lot_txs`, leaving the index that
+// blocks maintain untouched; returns the number of indexed transactions.
+pub fn rebuild_lot_txs(fork: &mut Fork) -> u64 {
+    let last_height = Schema::new(&*fork).height().0;
+    let txs: Vec<(Hash, AuctionTransactions)> = (0..=last_height)
+        .flat_map(|height| block_lot_txs(fork, Height(height)))
+        .collect();
+    let mut schema = AuctionSchema::new(fork);
+    let lot_pub_keys: HashSet<PublicKey> = schema.lots().keys()
+        .chain(txs.iter().filter_map(|(_, tx)| tx.lot_pub_key().cloned()))
+        .collect();
+    for lot_pub_key in &lot_pub_keys {
+        schema.rebuilt_lot_txs_mut(lot_pub_key).clear();
+    }
+    let mut indexed = 0;
+    for (tx_hash, tx) in &txs {
+        if let Some(lot_pub_key) = tx.lot_pub_key() {
+            schema.rebuilt_lot_txs_mut(lot_pub_key).push(*tx_hash);
+            indexed += 1;
         }
     }
+    schema.rebuilt_lot_txs_height_mut().set(last_height);
+    indexed
+}
+
+
+// Failures that no later state can fix: a bad signature or a malformed payload.
+pub fn check_stateless(tx: &dyn Transaction) -> Result<(), ErrorInfo> {
+    if !tx.verify() {
+        return Err(Error::InvalidSignature.into())
+    }
+    match AuctionTransactions::tx_from_raw(tx.raw().clone()) {
+        Ok(tx) => tx.check_payload(),
+        Err(_) => Ok(())
+    }
+}
+
+
+fn pending_for_lot(snapshot: &dyn Snapshot, lot_pub_key: &PublicKey) -> bool {
+    let core_schema = Schema::new(snapshot);
+    let transactions = core_schema.transactions();
+    let pool = core_schema.transactions_pool();
+    let pending = pool.iter()
+        .filter_map(|tx_hash| transactions.get(&tx_hash))
+        .filter(|raw| raw.service_id() == AUCTION_SERVICE_ID)
+        .filter_map(|raw| AuctionTransactions::tx_from_raw(raw).ok())
+        .any(|tx| tx.lot_pub_key() == Some(lot_pub_key));
+    pending
+}
+
+
+// Lot failures that pending transactions cannot fix, checked for the transactions whose execution
+// looks the lot up first. A missing lot may still be created from the pool, but a closed one never reopens.
+pub fn check_lot_state(tx: &dyn Transaction, snapshot: &dyn Snapshot) -> Result<(), ErrorInfo> {
+    let tx = match AuctionTransactions::tx_from_raw(tx.raw().clone()) {
+        Ok(tx) => tx,
+        Err(_) => return Ok(())
+    };
+    let (lot_pub_key, closes) = match tx {
+        AuctionTransactions::TxCreateBid(ref tx) => (tx.lot_pub_key(), true),
+        AuctionTransactions::TxCloseLot(ref tx) => (tx.lot_pub_key(), true),
+        AuctionTransactions::TxRateCounterparty(ref tx) => (tx.lot_pub_key(), false),
+        _ => return Ok(())
+    };
+    match AuctionSchema::new(snapshot).lot(lot_pub_key) {
+        None if !pending_for_lot(snapshot, lot_pub_key) => Err(Error::LotNotFound.with("lot_pub_key", lot_pub_key)),
+        Some(ref lot) if closes && !lot.opened() => Err(Error::LotClosed.with("lot_pub_key", lot_pub_key)),
+        _ => Ok(())
+    }
+}
+
+
+// Executes against a throwaway fork of committed state, so the outcome is known without
+// broadcasting anything. Pool transactions are not applied first, so the answer is advisory.
+pub fn dry_run(tx: &dyn Transaction, mut fork: Fork) -> Result<(), ErrorInfo> {
+    check_stateless(tx)?;
+    match panic::catch_unwind(AssertUnwindSafe(|| tx.execute(&mut fork))) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(ErrorInfo::from(&TransactionError::from(e))),
+        Err(_) => Err(Error::ExecutionPanic.into())
+    }
+}
+
+
+// Moderated lots take no bids and cannot be settled.
+fn check_not_moderated(schema: &AuctionSchema<&mut Fork>, lot_pub_key: &PublicKey) -> Result<(), ErrorInfo> {
+    match schema.lot_moderation().get(lot_pub_key) {
+        Some(ref moderation) if moderation.delisted() => Err(Error::LotDelisted.with("lot_pub_key", lot_pub_key).with("reason", moderation.reason())),
+        Some(ref moderation) if moderation.suspended() => Err(Error::LotSuspended.with("lot_pub_key", lot_pub_key).with("reason", moderation.reason())),
+        _ => Ok(())
+    }
+}
+
+
+// The secret key is revealed on close, so it has to be checked against the key bids were sealed for.
+fn session_keys_match(pub_session_key: &PublicKey, secret_session_key: &SecretKey) -> bool {
+    crypto::verify(&crypto::sign(pub_session_key.as_ref(), secret_session_key), pub_session_key.as_ref(), pub_session_key)
+}
+
+
+impl TxCreateLot {
+    // The seller's consent to list under the lot key; the transaction itself is signed by the lot key.
+    pub fn sign_lot_key(lot_pub_key: &PublicKey, seller_key: &SecretKey) -> Signature {
+        crypto::sign(lot_pub_key.as_ref(), seller_key)
+    }
 }
 
 
 impl Transaction for TxCreateLot {
     fn verify(&self) -> bool {
         self.verify_signature(self.pub_key())
+            && crypto::verify(self.seller_signature(), self.pub_key().as_ref(), self.seller_pub_key())
     }
 
     fn execute(&self, view: &mut Fork) -> ExecutionResult {
         let mut schema = AuctionSchema::new(view);
+        if schema.is_banned(self.seller_pub_key()) {
+            Err(Error::MemberBanned.with("pub_key", self.seller_pub_key()))?
+        }
+        let max_description_length = schema.config().max_description_length;
+        if max_description_length > 0 && self.description().len() as u64 > max_description_length {
+            Err(Error::DescriptionTooLong.with("length", self.description().len()).with("max_description_length", max_description_length))?
+        }
         if schema.lot(self.pub_key()).is_none() {
-            let lot = Lot::new(self.pub_key(), self.pub_session_key(), self.token_hash(), self.description(), self.price(), true, 0);
+            let lot = Lot::new(
+                self.pub_key(), self.seller_pub_key(), self.pub_session_key(), self.token_hash(), self.media_hashes(), self.description(),
+                self.price(), true, 0, self.min_reputation()
+            );
             println!("Create the lot: {:?}", lot);
-            schema.lots_mut().put(self.pub_key(), lot);
+            for media_hash in lot.media_hashes() {
+                schema.media_mut().insert(*media_hash);
+            }
+            schema.save_lot(lot, &self.hash());
+            schema.update_stats(|stats| stats.add_lot());
             Ok(())
         } else {
-            Err(Error::LotAlreadyExists)?
+            Err(Error::LotAlreadyExists.with("lot_pub_key", self.pub_key()))?
         }
     }
 }
 
 
+impl TxCreateBid {
+    // Proof that the bidder holds the bid key; the transaction itself is signed by the member key.
+    pub fn sign_member_key(member_pub_key: &PublicKey, bid_key: &SecretKey) -> Signature {
+        crypto::sign(member_pub_key.as_ref(), bid_key)
+    }
+
+    pub fn check_payload(&self) -> Result<(), ErrorInfo> {
+        if !Bid::new(self.pub_key(), self.lot_pub_key(), 0, 0, true, self.data()).is_sealed() {
+            return Err(Error::BadCiphertext.with("bid_pub_key", self.pub_key()).with("length", self.data().len()))
+        }
+        Ok(())
+    }
+}
+
+
 impl Transaction for TxCreateBid {
+    // Signed by the member rather than the bid key, so that bans and reputation apply to the bidder.
     fn verify(&self) -> bool {
-        self.verify_signature(self.pub_key())
+        self.verify_signature(self.member_pub_key())
+            && crypto::verify(self.bid_signature(), self.member_pub_key().as_ref(), self.pub_key())
     }
 
     fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        self.check_payload()?;
         let mut schema = AuctionSchema::new(view);
-        if schema.lot(self.lot_pub_key()).is_none() {
-            Err(Error::LotNotFound)?
+        let min_reputation = match schema.lot(self.lot_pub_key()) {
+            None => Err(Error::LotNotFound.with("lot_pub_key", self.lot_pub_key()))?,
+            Some(ref lot) if !lot.opened() => Err(Error::LotClosed.with("lot_pub_key", self.lot_pub_key()))?,
+            Some(lot) => lot.min_reputation()
+        };
+        check_not_moderated(&schema, self.lot_pub_key())?;
+        if schema.is_banned(self.member_pub_key()) {
+            Err(Error::MemberBanned.with("pub_key", self.member_pub_key()))?
+        }
+        let score = schema.reputation(self.member_pub_key()).score();
+        if min_reputation > 0 && score < min_reputation as i64 {
+            Err(Error::ReputationTooLow.with("score", score).with("min_reputation", min_reputation))?
+        }
+        if schema.bid(self.pub_key()).is_some() {
+            Err(Error::BidAlreadyExists.with("bid_pub_key", self.pub_key()))?
         }
         let bid = Bid::new(self.pub_key(), self.lot_pub_key(), 0, 0, true, self.data());
         println!("Create the bid: {:?}", bid);
         schema.lot_bids_mut(self.lot_pub_key()).push(*bid.pub_key());
-        schema.bids_mut().put(self.pub_key(), bid);
+        schema.bid_members_mut().put(bid.pub_key(), *self.member_pub_key());
+        schema.save_bid(bid, &self.hash());
+        schema.update_stats(|stats| stats.add_bid());
         Ok(())
     }
 }
@@ -82,9 +358,19 @@ impl Transaction for TxCloseLot {
         let mut schema = AuctionSchema::new(view);
         let lot = schema.lot(self.lot_pub_key());
         if lot.is_none() {
-            Err(Error::LotNotFound)?
+            Err(Error::LotNotFound.with("lot_pub_key", self.lot_pub_key()))?
         };
         let lot = lot.unwrap();
+        if !lot.opened() {
+            Err(Error::LotClosed.with("lot_pub_key", self.lot_pub_key()))?
+        }
+        if lot.pub_session_key() != self.pub_session_key() {
+            Err(Error::SessionKeyMismatch.with("lot_pub_key", self.lot_pub_key()))?
+        }
+        check_not_moderated(&schema, self.lot_pub_key())?;
+        let secret_session_key = SecretKey::from_hex(self.secret_session_key()).ok()
+            .filter(|secret_session_key| session_keys_match(self.pub_session_key(), secret_session_key))
+            .ok_or_else(|| Error::SessionKeyMismatch.with("lot_pub_key", self.lot_pub_key()).with("reason", "Secret session key does not match"))?;
         let bid_pub_keys: Vec<PublicKey>;
         {
             let lot_bids = schema.lot_bids_mut(self.lot_pub_key());
@@ -92,20 +378,258 @@ impl Transaction for TxCloseLot {
         }
         let mut winner_member_id: u64 = 0;
         let mut winner_sum: u64 = 0;
+        let mut winner_bid: Option<PublicKey> = None;
         for bid_pub_key in bid_pub_keys.iter() {
             let bid = schema.bids_mut().get(&bid_pub_key).unwrap();
+            // A bid that does not open stays sealed and takes no part in the auction.
+            let bid = if bid.encrypted() {
+                match bid.clone().decrypt(self.pub_session_key(), &secret_session_key) {
+                    Some(opened) => opened,
+                    None => continue
+                }
+            } else {
+                bid
+            };
             if bid.sum() > winner_sum {
                 winner_member_id = bid.member_id();
                 winner_sum = bid.sum();
+                winner_bid = Some(*bid_pub_key);
             }
-            // TODO: fix decrypt
-            //let bid = bid.decrypt(self.pub_session_key(), self.secret_session_key());
-
-            schema.bids_mut().put(&bid_pub_key, bid);
+            schema.save_bid(bid, &self.hash());
+        }
+        // Sums are sealed until close, so this is the first point where the lot price can be checked.
+        if winner_bid.is_some() && winner_sum < lot.price() {
+            Err(Error::BidTooLow.with("lot_pub_key", self.lot_pub_key()).with("price", lot.price()).with("sum", winner_sum))?
         }
+        if let Some(winner) = winner_bid.and_then(|bid_pub_key| schema.bid_members().get(&bid_pub_key)) {
+            schema.lot_winners_mut().put(self.lot_pub_key(), winner);
+        }
+        schema.save_outcome(&lot, winner_sum, bid_pub_keys.len() as u64);
         let lot = lot.close(winner_member_id, winner_sum);
-        schema.lots_mut().put(self.lot_pub_key(), lot);
+        schema.save_lot(lot, &self.hash());
+
+        Ok(())
+    }
+}
+
 
+// Only votes of the current administrators count, since the set may have changed since the vote.
+// An accepted proposal supersedes the pending one, so a config with a far-off `actual_from` cannot block governance.
+fn vote_for_config(schema: &mut AuctionSchema<&mut Fork>, proposal_hash: &Hash, admin: &PublicKey) {
+    schema.config_votes_mut(proposal_hash).insert(*admin);
+    let config = schema.config();
+    let votes = schema.config_votes(proposal_hash).iter().filter(|voter| config.is_admin(voter)).count() as u64;
+    if votes >= config.required_votes() {
+        let proposal = schema.config_proposals().get(proposal_hash).unwrap();
+        schema.config_proposals_mut().put(proposal_hash, proposal.accept());
+        schema.pending_config_mut().set(*proposal_hash);
+    }
+}
+
+
+impl Transaction for TxProposeConfig {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let mut schema = AuctionSchema::new(view);
+        if !schema.config().is_admin(self.from()) {
+            Err(Error::NotAdmin.with("pub_key", self.from()))?
+        }
+        let config: ServiceConfig = serde_json::from_str(self.config())
+            .map_err(|e| Error::InvalidConfig.with("reason", e.to_string()))?;
+        if config.admin_keys.is_empty() {
+            Err(Error::InvalidConfig.with("reason", "No administrators left to change the config"))?
+        }
+        if config.required_votes() > config.admin_keys.len() as u64 {
+            Err(Error::InvalidConfig.with("reason", "Vote threshold exceeds the number of administrators"))?
+        }
+        let height = schema.current_height();
+        if self.actual_from() <= height {
+            Err(Error::InvalidConfig.with("reason", "actual_from is not in the future").with("height", height))?
+        }
+        let proposal = ConfigProposal::new(self.from(), self.config(), self.actual_from(), false);
+        schema.config_proposals_mut().put(&self.hash(), proposal);
+        vote_for_config(&mut schema, &self.hash(), self.from());
+        Ok(())
+    }
+}
+
+
+impl Transaction for TxVoteConfig {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let mut schema = AuctionSchema::new(view);
+        if !schema.config().is_admin(self.from()) {
+            Err(Error::NotAdmin.with("pub_key", self.from()))?
+        }
+        let proposal = match schema.config_proposals().get(self.proposal_hash()) {
+            Some(proposal) => proposal,
+            None => Err(Error::ProposalNotFound.with("proposal_hash", self.proposal_hash()))?
+        };
+        if proposal.accepted() {
+            Err(Error::ProposalClosed.with("proposal_hash", self.proposal_hash()).with("reason", "Proposal is already accepted"))?
+        }
+        if proposal.actual_from() <= schema.current_height() {
+            Err(Error::ProposalClosed.with("proposal_hash", self.proposal_hash()).with("reason", "Proposal has expired"))?
+        }
+        if schema.config_votes(self.proposal_hash()).contains(self.from()) {
+            Err(Error::AlreadyVoted.with("proposal_hash", self.proposal_hash()).with("pub_key", self.from()))?
+        }
+        vote_for_config(&mut schema, self.proposal_hash(), self.from());
+        Ok(())
+    }
+}
+
+
+// The service holds no escrow yet, so moderation has no funds to refund; it only freezes the lot.
+fn moderate_lot(schema: &mut AuctionSchema<&mut Fork>, admin: &PublicKey, lot_pub_key: &PublicKey, suspended: bool, delisted: bool, reason: &str) -> Result<(), ErrorInfo> {
+    if !schema.config().is_admin(admin) {
+        return Err(Error::NotAdmin.with("pub_key", admin))
+    }
+    if schema.lot(lot_pub_key).is_none() {
+        return Err(Error::LotNotFound.with("lot_pub_key", lot_pub_key))
+    }
+    if schema.is_delisted(lot_pub_key) {
+        return Err(Error::LotDelisted.with("lot_pub_key", lot_pub_key))
+    }
+    // A closed lot can still be suspended to freeze its settlement, until it is settled.
+    if suspended && schema.settlements().contains(lot_pub_key) {
+        return Err(Error::LotAlreadySettled.with("lot_pub_key", lot_pub_key))
+    }
+    let moderation = LotModeration::new(suspended, delisted, reason, admin, schema.current_height());
+    schema.lot_moderation_mut().put(lot_pub_key, moderation);
+    Ok(())
+}
+
+
+impl Transaction for TxSuspendLot {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let mut schema = AuctionSchema::new(view);
+        moderate_lot(&mut schema, self.from(), self.lot_pub_key(), self.suspended(), false, self.reason())?;
+        Ok(())
+    }
+}
+
+
+impl Transaction for TxDelistLot {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let mut schema = AuctionSchema::new(view);
+        moderate_lot(&mut schema, self.from(), self.lot_pub_key(), false, true, self.reason())?;
+        Ok(())
+    }
+}
+
+
+impl Transaction for TxBanMember {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        let mut schema = AuctionSchema::new(view);
+        if !schema.config().is_admin(self.from()) {
+            Err(Error::NotAdmin.with("pub_key", self.from()))?
+        }
+        let ban = MemberBan::new(self.banned(), self.reason(), self.from(), schema.current_height());
+        schema.member_bans_mut().put(self.member_pub_key(), ban);
+        Ok(())
+    }
+}
+
+
+impl TxSettleLot {
+    pub fn check_payload(&self) -> Result<(), ErrorInfo> {
+        SettlementOutcome::from_u8(self.outcome()).map(|_| ()).ok_or_else(|| Error::InvalidOutcome.with("outcome", self.outcome()))
+    }
+}
+
+
+// Closing a lot does not move any funds, so administrators report how the deal ended.
+impl Transaction for TxSettleLot {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        self.check_payload()?;
+        let mut schema = AuctionSchema::new(view);
+        if !schema.config().is_admin(self.from()) {
+            Err(Error::NotAdmin.with("pub_key", self.from()))?
+        }
+        let lot = schema.lot(self.lot_pub_key())
+            .ok_or_else(|| Error::LotNotFound.with("lot_pub_key", self.lot_pub_key()))?;
+        check_not_moderated(&schema, self.lot_pub_key())?;
+        let winner = schema.lot_winners().get(self.lot_pub_key())
+            .ok_or_else(|| Error::LotNotSold.with("lot_pub_key", self.lot_pub_key()))?;
+        if schema.settlements().contains(self.lot_pub_key()) {
+            Err(Error::LotAlreadySettled.with("lot_pub_key", self.lot_pub_key()))?
+        }
+        let settlement = Settlement::new(&winner, self.outcome(), self.from(), schema.current_height());
+        schema.settlements_mut().put(self.lot_pub_key(), settlement);
+        let outcome = SettlementOutcome::from_u8(self.outcome()).unwrap();
+        schema.update_reputation(lot.seller_pub_key(), |reputation| reputation.add_outcome(outcome, true));
+        schema.update_reputation(&winner, |reputation| reputation.add_outcome(outcome, false));
+        Ok(())
+    }
+}
+
+
+impl TxRateCounterparty {
+    pub fn check_payload(&self) -> Result<(), ErrorInfo> {
+        if self.rating() == 0 || self.rating() > MAX_RATING {
+            return Err(Error::InvalidRating.with("rating", self.rating()).with("max_rating", MAX_RATING))
+        }
+        if self.review().len() > MAX_REVIEW_LENGTH {
+            return Err(Error::InvalidRating.with("length", self.review().len()).with("max_review_length", MAX_REVIEW_LENGTH))
+        }
+        Ok(())
+    }
+}
+
+
+// The seller and the winner rate each other once the deal is settled, whatever its outcome.
+impl Transaction for TxRateCounterparty {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, view: &mut Fork) -> ExecutionResult {
+        self.check_payload()?;
+        let mut schema = AuctionSchema::new(view);
+        let lot = schema.lot(self.lot_pub_key())
+            .ok_or_else(|| Error::LotNotFound.with("lot_pub_key", self.lot_pub_key()))?;
+        let settlement = schema.settlements().get(self.lot_pub_key())
+            .ok_or_else(|| Error::LotNotSettled.with("lot_pub_key", self.lot_pub_key()))?;
+        let counterparty = if self.from() == lot.seller_pub_key() {
+            *settlement.winner()
+        } else if self.from() == settlement.winner() {
+            *lot.seller_pub_key()
+        } else {
+            Err(Error::NotLotParty.with("pub_key", self.from()).with("lot_pub_key", self.lot_pub_key()))?
+        };
+        // A seller who won their own lot would otherwise raise their own reputation.
+        if counterparty == *self.from() {
+            Err(Error::SelfRating.with("pub_key", self.from()).with("lot_pub_key", self.lot_pub_key()))?
+        }
+        if schema.lot_ratings(self.lot_pub_key()).contains(self.from()) {
+            Err(Error::AlreadyRated.with("pub_key", self.from()).with("lot_pub_key", self.lot_pub_key()))?
+        }
+        let rating = Rating::new(self.lot_pub_key(), self.from(), &counterparty, self.rating(), self.review(), schema.current_height());
+        schema.lot_ratings_mut(self.lot_pub_key()).put(self.from(), rating.clone());
+        schema.member_ratings_mut(&counterparty).push(rating);
         Ok(())
     }
 }
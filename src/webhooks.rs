@@ -0,0 +1,189 @@
+extern crate exonum_sodiumoxide;
+
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use exonum::crypto::{self, Hash};
+use exonum::encoding::serialize::encode_hex;
+use exonum::storage::Snapshot;
+use serde_json;
+use schema::AuctionSchema;
+use reqwest;
+use self::exonum_sodiumoxide::crypto::auth::hmacsha256;
+use events::{AuctionEvent, EventFilter};
+use reqwest::header::{ContentType, Headers};
+
+
+pub const SIGNATURE_HEADER: &str = "X-Auction-Signature";
+pub const EVENT_HEADER: &str = "X-Auction-Event";
+pub const DEFAULT_ATTEMPTS: u32 = 5;
+pub const DEFAULT_BACKOFF_MS: u64 = 250;
+pub const QUEUE_CAPACITY: usize = 1024;
+pub const WORKERS: usize = 4;
+pub const REDACTED_SECRET: &str = "<redacted>";
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Webhook {
+    pub url: String,
+    pub secret: String,
+    #[serde(default)]
+    pub filter: EventFilter,
+    // Event types to deliver, e.g. `lot_closed`; empty means every type.
+    #[serde(default)]
+    pub events: Vec<String>
+}
+
+
+impl Webhook {
+    pub fn id(&self) -> Hash {
+        crypto::hash(serde_json::to_string(self).unwrap_or_default().as_bytes())
+    }
+
+    pub fn matches(&self, event: &AuctionEvent) -> bool {
+        self.filter.matches(event) && (self.events.is_empty() || self.events.iter().any(|kind| kind == event.kind()))
+    }
+
+    pub fn redacted(self) -> Self {
+        Webhook { secret: REDACTED_SECRET.to_owned(), ..self }
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub backoff_ms: u64
+}
+
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { attempts: DEFAULT_ATTEMPTS, backoff_ms: DEFAULT_BACKOFF_MS }
+    }
+}
+
+
+impl RetryPolicy {
+    // Exponential backoff: `backoff_ms`, then twice that, and so on.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        Duration::from_millis(self.backoff_ms << attempt.min(16))
+    }
+}
+
+
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut state = hmacsha256::State::init(secret.as_bytes());
+    state.update(payload);
+    format!("sha256={}", encode_hex(&state.finalize()[..]))
+}
+
+
+fn post(client: &reqwest::Client, webhook: &Webhook, kind: &str, payload: &str) -> bool {
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers.set_raw(SIGNATURE_HEADER, sign(&webhook.secret, payload.as_bytes()));
+    headers.set_raw(EVENT_HEADER, kind.to_owned());
+    client.post(&webhook.url)
+        .headers(headers)
+        .body(payload.to_owned())
+        .send()
+        .map(|response| response.status().is_success())
+        .unwrap_or(false)
+}
+
+
+struct Delivery {
+    webhook: Webhook,
+    kind: &'static str,
+    height: u64,
+    payload: String,
+    policy: RetryPolicy
+}
+
+
+impl Delivery {
+    fn run(&self, client: &reqwest::Client) {
+        for attempt in 0..self.policy.attempts {
+            if post(client, &self.webhook, self.kind, &self.payload) {
+                return
+            }
+            if attempt + 1 < self.policy.attempts {
+                thread::sleep(self.policy.delay(attempt));
+            }
+        }
+        warn!(
+            "Webhook {} gave up on {} at height {} after {} attempts",
+            self.webhook.url, self.kind, self.height, self.policy.attempts
+        );
+    }
+}
+
+
+// A fixed pool of workers drains a bounded queue, so a slow receiver never holds up block commit
+// and a burst of events cannot spawn unbounded threads. Deliveries only live in memory: the ones
+// queued when the node stops are lost and can be replayed with `v1/webhooks/redeliver`.
+#[derive(Clone, Default)]
+pub struct WebhookQueue {
+    sender: Arc<Mutex<Option<mpsc::SyncSender<Delivery>>>>
+}
+
+
+impl fmt::Debug for WebhookQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookQueue").field("started", &self.sender.lock().unwrap().is_some()).finish()
+    }
+}
+
+
+impl WebhookQueue {
+    // Returns how many deliveries were queued; the rest are dropped with a warning.
+    pub fn dispatch(&self, webhooks: &[Webhook], events: &[AuctionEvent], policy: RetryPolicy) -> usize {
+        let mut sender = self.sender.lock().unwrap();
+        let sender = sender.get_or_insert_with(start_workers);
+        let mut queued = 0;
+        for webhook in webhooks {
+            for event in events.iter().filter(|event| webhook.matches(event)) {
+                let payload = match serde_json::to_string(event) {
+                    Ok(payload) => payload,
+                    Err(_) => continue
+                };
+                let height = event.height();
+                let delivery = Delivery { webhook: webhook.clone(), kind: event.kind(), height, payload, policy };
+                match sender.try_send(delivery) {
+                    Ok(()) => queued += 1,
+                    Err(_) => warn!("Webhook queue is full, dropped {} at height {} for {}", event.kind(), height, webhook.url)
+                }
+            }
+        }
+        queued
+    }
+}
+
+
+fn start_workers() -> mpsc::SyncSender<Delivery> {
+    let (sender, receiver) = mpsc::sync_channel::<Delivery>(QUEUE_CAPACITY);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..WORKERS {
+        let receiver = receiver.clone();
+        thread::spawn(move || {
+            let client = reqwest::Client::new();
+            loop {
+                let delivery = match receiver.lock().unwrap().recv() {
+                    Ok(delivery) => delivery,
+                    Err(_) => return
+                };
+                delivery.run(&client);
+            }
+        });
+    }
+    sender
+}
+
+
+pub fn registered<T: AsRef<dyn Snapshot>>(schema: &AuctionSchema<T>) -> Vec<(Hash, Webhook)> {
+    schema.webhooks().iter()
+        .filter_map(|(id, raw)| serde_json::from_str(&raw).ok().map(|webhook| (id, webhook)))
+        .collect()
+}
@@ -1,21 +1,50 @@
 #[macro_use]
 extern crate assert_matches;
+extern crate actix_web;
 extern crate exonum;
 extern crate ex_auction;
+#[macro_use]
 extern crate exonum_testkit;
+extern crate futures;
+extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use actix_web::{test::TestServer, ws, App};
+use futures::{Future, Stream};
 use exonum::{
-    api::{self, node::public::explorer::TransactionQuery},
+    api::{self, node::public::explorer::TransactionQuery, ApiAccess, ApiAggregator, ServiceApiState},
+    blockchain::SharedNodeState,
     crypto::{self, CryptoHash, Hash, PublicKey, SecretKey},
 };
 use exonum_testkit::{ApiKind, TestKit, TestKitApi, TestKitBuilder};
 
-use ex_auction::api::{LotQuery, BidQuery, LotBidsQuery};
-use ex_auction::schema::{Lot, Bid};
+use ex_auction::api::{
+    LotQuery, BidQuery, LotBidsQuery, LotHistoryQuery, BatchQuery, BatchResponse, LotsQuery, LotTxsQuery, LotTransaction, TxStatus,
+    StatsQuery, StatsReport, MediaQuery, Media, MediaResponse, WebhookQuery, WebhookEntry, PendingTransaction,
+    SchemaStats, RebuildReport, NodeSettings, TransactionResponse, DryRunResponse, TxStatusQuery, TxState, TransactionStatus,
+    ConfigProposalInfo, ConfigChange, LotModerationQuery, SettlementQuery, ReputationQuery, MemberReputation, RatingsQuery,
+    MemberRatings, RedeliverQuery, RedeliverReport
+};
+use ex_auction::schema::{AuctionSchema, Lot, Bid, LotRecord, LotModeration, ServiceConfig, Reputation, Settlement, SettlementOutcome};
+use ex_auction::consistency::{ConsistencyReport, Inconsistency};
+use ex_auction::errors::{Error, ErrorInfo};
+use ex_auction::events::{AuctionEvent, EventFilter};
+use ex_auction::webhooks::{self, Webhook, RetryPolicy};
+use ex_auction::media::MediaConfig;
+use ex_auction::config::LocalSettings;
 use ex_auction::AuctionService;
-use ex_auction::transactions::{TxCreateLot, TxCreateBid, TxCloseLot};
+use ex_auction::transactions::{
+    TxCreateLot, TxCreateBid, TxCloseLot, TxProposeConfig, TxVoteConfig, TxSuspendLot, TxDelistLot, TxBanMember, TxSettleLot,
+    TxRateCounterparty
+};
+use ex_auction::routes::VersionInfo;
 use ex_auction::AUCTION_SERVICE_NAME;
 
 
@@ -24,7 +53,7 @@ fn test_create_lot() {
     let (mut testkit, api) = create_testkit();
     let token_hash = crypto::hash(&[0]);
     let description = "Lot 1";
-    let (tx, _, _, _) = api.create_lot(&token_hash, description, 100);
+    let (tx, _, _, _) = api.create_lot(&token_hash, &[], description, 100);
     testkit.create_block();
     api.assert_tx_status(tx.hash(), &json!({ "type": "success" }));
 
@@ -33,6 +62,12 @@ fn test_create_lot() {
     assert_eq!(lot.token_hash(), tx.token_hash());
     assert_eq!(lot.description(), tx.description());
     assert_eq!(lot.price(), 100);
+
+    // Taking an existing lot key requires its secret key, whatever the seller signs.
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(tx.pub_key(), &seller_key);
+    let squat_tx = TxCreateLot::new(tx.pub_key(), &seller_pub_key, &seller_signature, tx.pub_session_key(), &token_hash, &[], "Lot 1b", 100, 0, &seller_key);
+    assert_eq!(api.post_error("v1/lot", &squat_tx).code, Error::InvalidSignature.code());
 }
 
 
@@ -41,7 +76,7 @@ fn test_create_bid() {
     let (mut testkit, api) = create_testkit();
     let token_hash = crypto::hash(&[0]);
     let description = "Lot 2";
-    let (lot_tx, _, _, _) = api.create_lot(&token_hash, description, 100);
+    let (lot_tx, _, _, _) = api.create_lot(&token_hash, &[], description, 100);
     testkit.create_block();
 
     api.assert_tx_status(lot_tx.hash(), &json!({ "type": "success" }));
@@ -61,7 +96,7 @@ fn test_bids_count() {
     let (mut testkit, api) = create_testkit();
     let token_hash = crypto::hash(&[0]);
     let description = "Lot 3";
-    let (lot_tx, _, _, _) = api.create_lot(&token_hash, description, 100);
+    let (lot_tx, _, _, _) = api.create_lot(&token_hash, &[], description, 100);
     testkit.create_block();
 
     api.assert_tx_status(lot_tx.hash(), &json!({ "type": "success" }));
@@ -81,7 +116,7 @@ fn test_close_lot1() {
     let (mut testkit, api) = create_testkit();
     let token_hash = crypto::hash(&[0]);
     let description = "Lot 4";
-    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_lot(&token_hash, description, 100);
+    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_lot(&token_hash, &[], description, 100);
     testkit.create_block();
 
     api.assert_tx_status(lot_tx.hash(), &json!({ "type": "success" }));
@@ -99,10 +134,1201 @@ fn test_close_lot1() {
 }
 
 
+#[test]
+fn test_batch_lookup() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx1, _, _, _) = api.create_lot(&token_hash, &[], "Lot 10", 100);
+    let (lot_tx2, _, _, _) = api.create_lot(&token_hash, &[], "Lot 11", 100);
+    testkit.create_block();
+    let (bid_tx, _) = api.create_bid(lot_tx1.pub_key(), lot_tx1.pub_session_key(), 101, 1);
+    testkit.create_block();
+
+    let (unknown_key, _) = crypto::gen_keypair();
+    let query = BatchQuery { pub_keys: vec![*lot_tx1.pub_key(), unknown_key, *lot_tx2.pub_key()], height: None, include_delisted: false };
+    let lots: BatchResponse<Lot> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&query)
+        .post("v1/lots/batch")
+        .unwrap();
+    let found: Vec<&PublicKey> = lots.found.iter().map(|lot| lot.pub_key()).collect();
+    assert_eq!(found, vec![lot_tx1.pub_key(), lot_tx2.pub_key()]);
+    assert_eq!(lots.missing, vec![unknown_key]);
+
+    let query = BatchQuery { pub_keys: vec![*bid_tx.pub_key(), unknown_key], height: None, include_delisted: false };
+    let bids: BatchResponse<Bid> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&query)
+        .post("v1/bids/batch")
+        .unwrap();
+    assert_eq!(bids.found.len(), 1);
+    assert_eq!(bids.missing, vec![unknown_key]);
+}
+
+
+#[test]
+fn test_dangling_bid() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx, _, _, _) = api.create_lot(&token_hash, &[], "Lot 12", 100);
+    testkit.create_block();
+    let (bid_tx, _) = api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 101, 1);
+    testkit.create_block();
+    let report: ConsistencyReport = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/check_consistency")
+        .unwrap();
+    assert_eq!(report.lots_checked, 1);
+    assert_eq!(report.bids_checked, 1);
+    assert!(report.inconsistencies.is_empty());
+
+    let mut fork = testkit.blockchain().fork();
+    AuctionSchema::new(&mut fork).bids_mut().remove(bid_tx.pub_key());
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+
+    let result: api::Result<Vec<Bid>> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotBidsQuery { lot_pub_key: *lot_tx.pub_key(), height: None })
+        .get("v1/lot_bids");
+    assert_matches!(result, Err(api::Error::InternalError(_)));
+
+    let report: ConsistencyReport = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/check_consistency")
+        .unwrap();
+    assert_eq!(report.inconsistencies, vec![
+        Inconsistency::DanglingBid { lot_pub_key: *lot_tx.pub_key(), bid_pub_key: *bid_tx.pub_key() }
+    ]);
+}
+
+
+#[test]
+fn test_lot_at_height() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_lot(&token_hash, &[], "Lot 6", 100);
+    let lot_height = testkit.create_block().height().0;
+    let lot = api.get_lot(*lot_tx.pub_key());
+    api.create_bid(lot.pub_key(), lot.pub_session_key(), 101, 1);
+    let bid_height = testkit.create_block().height().0;
+    api.close_lot(lot.pub_key(), &pub_session_key, &secret_session_key, &secret_key);
+    testkit.create_block();
+
+    let opened: Lot = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotQuery { pub_key: *lot.pub_key(), height: Some(bid_height) })
+        .get("v1/lot")
+        .unwrap();
+    assert!(opened.opened());
+    assert!(!api.get_lot(*lot.pub_key()).opened());
+
+    let bids: Vec<Bid> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotBidsQuery { lot_pub_key: *lot.pub_key(), height: Some(lot_height) })
+        .get("v1/lot_bids")
+        .unwrap();
+    assert!(bids.is_empty());
+    assert_eq!(api.get_lot_bids(*lot.pub_key()).len(), 1);
+
+    let history: Vec<LotRecord> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotHistoryQuery { pub_key: *lot.pub_key() })
+        .get("v1/lot_history")
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].height(), lot_height);
+    assert_eq!(history[0].tx_hash(), &lot_tx.hash());
+}
+
+
+#[test]
+fn test_lot_txs() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 7", 100);
+    testkit.create_block();
+    let lot = api.get_lot(*lot_tx.pub_key());
+    let duplicate_tx = TxCreateLot::new(lot.pub_key(), &seller_pub_key, &TxCreateLot::sign_lot_key(lot.pub_key(), &seller_key), &pub_session_key, &token_hash, &[], "Lot 7", 200, 0, &secret_key);
+    testkit.create_block_with_transaction(duplicate_tx.clone());
+    let (bid_tx, _) = api.create_bid(lot.pub_key(), lot.pub_session_key(), 101, 1);
+    testkit.create_block();
+    let close_lot_tx = api.close_lot(lot.pub_key(), &pub_session_key, &secret_session_key, &secret_key);
+    testkit.create_block();
+
+    let txs: Vec<LotTransaction> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotTxsQuery { lot_pub_key: *lot.pub_key() })
+        .get("v1/lot_txs")
+        .unwrap();
+    let hashes: Vec<Hash> = txs.iter().map(|tx| tx.tx_hash).collect();
+    assert_eq!(hashes, vec![lot_tx.hash(), duplicate_tx.hash(), bid_tx.hash(), close_lot_tx.hash()]);
+    assert_eq!(txs[0].status, TxStatus::Success);
+    assert_matches!(txs[1].status, TxStatus::Error { code: 0, .. });
+    assert!(txs.windows(2).all(|pair| pair[0].height < pair[1].height));
+}
+
+
+#[test]
+fn test_stats() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (lot_tx1, pub_session_key, secret_session_key, secret_key) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 8", 100);
+    let (lot_tx2, _, _, _) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 9", 100);
+    let from = testkit.create_block().height().0;
+    let lot = api.get_lot(*lot_tx1.pub_key());
+    api.create_bid(lot.pub_key(), lot.pub_session_key(), 101, 1);
+    api.create_bid(lot.pub_key(), lot.pub_session_key(), 102, 2);
+    api.create_bid(lot_tx2.pub_key(), lot.pub_session_key(), 103, 3);
+    testkit.create_block();
+    api.close_lot(lot.pub_key(), &pub_session_key, &secret_session_key, &secret_key);
+    testkit.create_block();
+
+    let stats = api.get_stats(StatsQuery { from: None, to: None });
+    assert_eq!(stats.open_lots, 1);
+    assert_eq!(stats.closed_lots, 1);
+    assert_eq!(stats.lots_created, 2);
+    assert_eq!(stats.bids, 3);
+    assert_eq!(stats.bids_per_lot.get(&2), Some(&1));
+    assert_eq!(stats.top_sellers.len(), 1);
+    assert_eq!(stats.top_sellers[0].seller_pub_key, seller_pub_key);
+    assert_eq!(stats.top_sellers[0].lots, 1);
+
+    let stats = api.get_stats(StatsQuery { from: Some(from + 1), to: None });
+    assert_eq!(stats.lots_created, 0);
+    assert_eq!(stats.lots_closed, 1);
+    assert_eq!(stats.bids, 3);
+
+    let stats = api.get_stats(StatsQuery { from: None, to: Some(from) });
+    assert_eq!(stats.open_lots, 2);
+    assert_eq!(stats.lots_closed, 0);
+    assert!(stats.top_sellers.is_empty());
+}
+
+
+#[test]
+fn test_lot_media() {
+    let (mut testkit, api) = create_testkit();
+    let media = Media { mime_type: "image/png".to_owned(), data: "89504e47".to_owned() };
+    let media_hash = api.post_media(&media).hash;
+    assert_matches!(api.get_media(media_hash), Err(api::Error::NotFound(_)));
+
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx, _, _, _) = api.create_lot(&token_hash, &[media_hash], "Lot 5", 100);
+    testkit.create_block();
+    api.assert_tx_status(lot_tx.hash(), &json!({ "type": "success" }));
+
+    let lot = api.get_lot(*lot_tx.pub_key());
+    assert_eq!(lot.media_hashes(), &[media_hash]);
+    let stored = api.get_media(media_hash).unwrap();
+    assert_eq!(stored.mime_type, media.mime_type);
+    assert_eq!(stored.data, media.data);
+    let retyped = Media { mime_type: "application/pdf".to_owned(), data: media.data.clone() };
+    assert_ne!(api.post_media(&retyped).hash, media_hash);
+    assert_eq!(api.get_media(media_hash).unwrap().mime_type, media.mime_type);
+
+    let script = Media { mime_type: "text/html".to_owned(), data: "3c703e".to_owned() };
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&script).post::<MediaResponse>("v1/media"),
+        Err(api::Error::BadRequest(_))
+    );
+}
+
+
+#[test]
+fn test_error_codes() {
+    let (mut testkit, api) = create_testkit();
+    let (unknown_key, _) = crypto::gen_keypair();
+    match api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&LotQuery { pub_key: unknown_key, height: None }).get::<Lot>("v1/lot") {
+        Err(api::Error::NotFound(body)) => {
+            let error: ErrorInfo = serde_json::from_str(&body).unwrap();
+            assert_eq!(error.code, Error::LotNotFound.code());
+            assert_eq!(error.message, "Lot is not found");
+            assert_eq!(error.context["lot_pub_key"], json!(unknown_key));
+        },
+        other => panic!("Unexpected response: {:?}", other)
+    }
+
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx, _, _, _) = api.create_lot(&token_hash, &[], "Lot 13", 100);
+    testkit.create_block();
+    let (bid_key, bid_secret_key) = crypto::gen_keypair();
+    let bid_signature = TxCreateBid::sign_member_key(&bid_key, &bid_secret_key);
+    let unsealed_tx = TxCreateBid::new(&bid_key, lot_tx.pub_key(), &bid_key, &bid_signature, vec![1, 2, 3], &bid_secret_key);
+    let (orphan_tx, _) = sealed_bid(&unknown_key, lot_tx.pub_session_key(), 101, 1);
+    let unsealed = Error::BadCiphertext.with("bid_pub_key", bid_key).with("length", 3);
+    let orphan = Error::LotNotFound.with("lot_pub_key", unknown_key);
+    assert_eq!(api.post_error("v1/bid", &unsealed_tx), unsealed);
+    assert_eq!(api.post_error("v1/bid", &orphan_tx), orphan);
+
+    // Execution reports the same errors for transactions that bypass the API.
+    testkit.create_block_with_transaction(unsealed_tx.clone());
+    testkit.create_block_with_transaction(orphan_tx.clone());
+    api.assert_tx_status(unsealed_tx.hash(), &json!({ "type": "error", "code": 6, "description": unsealed.to_json() }));
+    api.assert_tx_status(orphan_tx.hash(), &json!({ "type": "error", "code": 1, "description": orphan.to_json() }));
+
+    let (lot_tx, _, secret_session_key, lot_key) = api.create_lot(&token_hash, &[], "Lot 15", 100);
+    testkit.create_block();
+    api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 60, 1);
+    api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 90, 2);
+    testkit.create_block();
+    let close_tx = api.close_lot(lot_tx.pub_key(), lot_tx.pub_session_key(), &secret_session_key, &lot_key);
+    testkit.create_block();
+    let error = Error::BidTooLow.with("lot_pub_key", lot_tx.pub_key()).with("price", 100).with("sum", 90);
+    assert_eq!(api.tx_error(close_tx.hash()), error);
+    assert!(api.get_lot(*lot_tx.pub_key()).opened());
+}
+
+
+#[test]
+fn test_tx_status() {
+    let (mut testkit, api) = create_testkit();
+    let (lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 14", 100);
+    testkit.poll_events();
+    let status = api.get_tx_status(lot_tx.hash()).unwrap();
+    assert_eq!(status.status, TxState::Pending);
+    assert_eq!(status.lot_pub_key, Some(*lot_tx.pub_key()));
+    assert_eq!(status.height, None);
+    assert_eq!(status.error, None);
+
+    testkit.create_block();
+    let status = api.get_tx_status(lot_tx.hash()).unwrap();
+    assert_eq!(status.status, TxState::Committed);
+    assert_eq!(status.height, Some(testkit.height().0));
+    assert_eq!(status.error, None);
+
+    // Submission rejects a bid on a missing lot, so it is committed directly.
+    let (unknown_key, _) = crypto::gen_keypair();
+    let (orphan_tx, _) = sealed_bid(&unknown_key, lot_tx.pub_session_key(), 101, 1);
+    testkit.create_block_with_transaction(orphan_tx.clone());
+    let status = api.get_tx_status(orphan_tx.hash()).unwrap();
+    assert_eq!(status.status, TxState::Failed);
+    assert_eq!(status.lot_pub_key, Some(unknown_key));
+    assert_eq!(status.height, Some(testkit.height().0));
+    assert_eq!(status.error, Some(Error::LotNotFound.with("lot_pub_key", unknown_key)));
+
+    let unknown_hash = crypto::hash(&[1, 2, 3]);
+    match api.get_tx_status(unknown_hash) {
+        Err(api::Error::NotFound(body)) => {
+            let error: ErrorInfo = serde_json::from_str(&body).unwrap();
+            assert_eq!(error, Error::TransactionNotFound.with("tx_hash", unknown_hash));
+        },
+        other => panic!("Unexpected response: {:?}", other)
+    }
+}
+
+
+#[test]
+fn test_dry_run() {
+    let (mut testkit, api) = create_testkit();
+    let (unknown_key, _) = crypto::gen_keypair();
+    let (lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 20", 100);
+    testkit.create_block();
+
+    let (orphan_tx, _) = sealed_bid(&unknown_key, lot_tx.pub_session_key(), 101, 1);
+    let response: DryRunResponse = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&orphan_tx)
+        .post("v1/dry_run")
+        .unwrap();
+    assert_eq!(response.tx_hash, orphan_tx.hash());
+    assert!(!response.success);
+    assert_eq!(response.error, Some(Error::LotNotFound.with("lot_pub_key", unknown_key)));
+    let (bid_tx, _) = sealed_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 101, 1);
+    let response: DryRunResponse = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&bid_tx)
+        .post("v1/dry_run")
+        .unwrap();
+    assert!(response.success);
+    testkit.poll_events();
+    assert!(!testkit.is_tx_in_pool(&bid_tx.hash()));
+
+    // Failures that no pending transaction can fix are rejected on submit.
+    assert_eq!(api.post_error("v1/bid", &orphan_tx), Error::LotNotFound.with("lot_pub_key", unknown_key));
+    let (pub_key, key) = crypto::gen_keypair();
+    let (seller_pub_key, _) = crypto::gen_keypair();
+    let (_, other_key) = crypto::gen_keypair();
+    let forged_tx = TxCreateLot::new(
+        &pub_key, &seller_pub_key, &TxCreateLot::sign_lot_key(&pub_key, &other_key), &pub_key, &crypto::hash(&[0]), &[], "Lot 21", 100, 0, &key
+    );
+    assert_eq!(api.post_error("v1/lot", &forged_tx), Error::InvalidSignature.into());
+
+    // A bid on a lot that is still in the pool is accepted and goes through once the lot is committed.
+    let (pending_lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 22", 100);
+    testkit.poll_events();
+    let (pending_bid_tx, _) = api.create_bid(pending_lot_tx.pub_key(), pending_lot_tx.pub_session_key(), 101, 1);
+    testkit.create_block_with_tx_hashes(&[pending_lot_tx.hash()]);
+    testkit.create_block();
+    assert_eq!(api.get_tx_status(pending_lot_tx.hash()).unwrap().status, TxState::Committed);
+    assert_eq!(api.get_tx_status(pending_bid_tx.hash()).unwrap().status, TxState::Committed);
+}
+
+
+#[test]
+fn test_typed_routes() {
+    let (mut testkit, api) = create_testkit();
+    let (pub_key, key) = crypto::gen_keypair();
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let tx = TxCreateLot::new(&pub_key, &seller_pub_key, &TxCreateLot::sign_lot_key(&pub_key, &seller_key), &pub_key, &crypto::hash(&[0]), &[], "Lot 14", 100, 0, &key);
+    for route in &["v1/bid", "v1/close_lot"] {
+        assert_matches!(
+            api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&tx).post::<serde_json::Value>(route),
+            Err(api::Error::BadRequest(_))
+        );
+    }
+    let close_tx = TxCloseLot::new(&pub_key, &pub_key, &key.to_hex(), &key);
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&close_tx).post::<serde_json::Value>("v1/lot"),
+        Err(api::Error::BadRequest(_))
+    );
+    testkit.create_block();
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&LotQuery { pub_key, height: None }).get::<Lot>("v1/lot"),
+        Err(api::Error::NotFound(_))
+    );
+}
+
+
+#[test]
+fn test_api_versions() {
+    let (mut testkit, api) = create_testkit();
+    let versions: Vec<VersionInfo> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("versions")
+        .unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].name, "v1");
+    assert!(!versions[0].deprecated);
+    assert!(versions[0].routes.contains(&"GET v1/lot".to_owned()));
+    assert!(versions[0].routes.contains(&"POST v1/close_lot".to_owned()));
+    assert!(!versions[0].routes.contains(&"GET v1/mempool".to_owned()));
+    assert!(versions[0].legacy_aliases.contains(&"lot".to_owned()));
+
+    // Clients written before versioning keep working, with the typed v1 handlers behind the old paths.
+    let (pub_key, key) = crypto::gen_keypair();
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let tx = TxCreateLot::new(&pub_key, &seller_pub_key, &TxCreateLot::sign_lot_key(&pub_key, &seller_key), &pub_key, &crypto::hash(&[0]), &[], "Lot 16", 100, 0, &key);
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&tx).post::<serde_json::Value>("bid"),
+        Err(api::Error::BadRequest(_))
+    );
+    let response: TransactionResponse = api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&tx).post("lot").unwrap();
+    assert_eq!(response.tx_hash, tx.hash());
+    testkit.create_block();
+    let lot: Lot = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotQuery { pub_key, height: None })
+        .get("lot")
+        .unwrap();
+    assert_eq!(lot, api.get_lot(pub_key));
+    let report: ConsistencyReport = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("check_consistency")
+        .unwrap();
+    assert_eq!(report.lots_checked, 1);
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).get::<serde_json::Value>("config"),
+        Err(api::Error::NotFound(_))
+    );
+}
+
+
+#[test]
+fn test_spec() {
+    let (mut testkit, api) = create_testkit();
+    let spec: serde_json::Value = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/spec")
+        .unwrap();
+    assert_eq!(spec["openapi"], "3.0.0");
+    let versions: Vec<VersionInfo> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("versions")
+        .unwrap();
+    for route in versions.iter().flat_map(|version| version.routes.iter()) {
+        let mut parts = route.splitn(2, ' ');
+        let (method, path) = (parts.next().unwrap().to_lowercase(), parts.next().unwrap());
+        assert!(spec["paths"][format!("/{}", path)][&method].is_object(), "{} is not documented", route);
+    }
+    assert_eq!(spec["paths"]["/lot"]["get"]["deprecated"], true);
+    assert_eq!(spec["paths"]["/v1/lot"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/TxCreateLot");
+    assert!(spec["paths"]["/v1/check_consistency"]["get"].is_object());
+    let schemas = &spec["components"]["schemas"];
+    let mut refs = Vec::new();
+    collect_refs(&spec, &mut refs);
+    for reference in refs {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        assert!(schemas[name].is_object(), "{} is not defined", reference);
+    }
+
+    let (lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 15", 100);
+    testkit.create_block();
+    let (bid_tx, _) = api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 101, 1);
+    testkit.create_block();
+    let shapes = vec![
+        ("Lot", serde_json::to_value(api.get_lot(*lot_tx.pub_key())).unwrap()),
+        ("Bid", serde_json::to_value(api.get_bid(*bid_tx.pub_key())).unwrap()),
+        ("TxCreateLot", serde_json::to_value(&lot_tx).unwrap()),
+        ("TxCreateBid", serde_json::to_value(&bid_tx).unwrap()),
+    ];
+    for (name, value) in shapes {
+        let mut documented: Vec<&str> = schemas[name]["properties"].as_object().unwrap().keys().map(|key| key.as_str()).collect();
+        let mut actual: Vec<&str> = value.as_object().unwrap().keys().map(|key| key.as_str()).collect();
+        documented.sort();
+        actual.sort();
+        assert_eq!(documented, actual, "{} shape differs", name);
+    }
+}
+
+
+#[test]
+fn test_events() {
+    let (admin, admin_key) = crypto::gen_keypair();
+    let service = AuctionService::default().with_config(ServiceConfig { admin_keys: vec![admin], ..ServiceConfig::default() });
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (sender, receiver) = mpsc::channel();
+    let hub = service.events().clone();
+    hub.subscribe(EventFilter { lot_pub_key: None, seller_pub_key: Some(seller_pub_key) }, Box::new(sender));
+    let mut testkit = TestKitBuilder::validator().with_service(service).create();
+    let api = AuctionApi { inner: testkit.api() };
+
+    let mut server = create_ws_server(&testkit);
+    let url = server.url(&format!("/public/api/services/{}/v1/events?seller_pub_key={}", AUCTION_SERVICE_NAME, seller_pub_key.to_hex()));
+    let (mut reader, _writer) = server.execute(ws::Client::new(url).connect()).unwrap();
+    // The session subscribes once its actor starts, which may lag behind the handshake.
+    while hub.len() < 2 {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 16", 100);
+    api.create_lot(&token_hash, &[], "Lot 17", 100);
+    let height = testkit.create_block().height().0;
+    let (bid_tx, _) = api.create_bid(lot_tx.pub_key(), &pub_session_key, 101, 1);
+    testkit.create_block();
+    let close_tx = api.close_lot(lot_tx.pub_key(), &pub_session_key, &secret_session_key, &secret_key);
+    testkit.create_block();
+    let settle_tx = TxSettleLot::new(&admin, lot_tx.pub_key(), SettlementOutcome::Completed as u8, &admin_key);
+    api.post_tx("v1/settle_lot", &settle_tx);
+    let settle_height = testkit.create_block().height().0;
+
+    let events: Vec<AuctionEvent> = receiver.try_iter().collect();
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0], AuctionEvent::LotCreated {
+        height, tx_hash: lot_tx.hash(), lot_pub_key: *lot_tx.pub_key(), seller_pub_key
+    });
+    assert_matches!(events[1], AuctionEvent::BidPlaced { bid_pub_key, .. } if bid_pub_key == *bid_tx.pub_key());
+    assert_matches!(events[2], AuctionEvent::LotClosed { tx_hash, bids: 1, .. } if tx_hash == close_tx.hash());
+    assert_eq!(events[3], AuctionEvent::LotSettled {
+        height: settle_height,
+        tx_hash: settle_tx.hash(),
+        lot_pub_key: *lot_tx.pub_key(),
+        seller_pub_key,
+        winner: *bid_tx.member_pub_key(),
+        outcome: SettlementOutcome::Completed as u8
+    });
+
+    for event in &events {
+        let (message, rest) = server.execute(reader.into_future().map_err(|(error, _)| error)).unwrap();
+        reader = rest;
+        match message {
+            Some(ws::Message::Text(text)) => assert_eq!(serde_json::from_str::<AuctionEvent>(&text).unwrap(), *event),
+            other => panic!("Unexpected message: {:?}", other)
+        }
+    }
+}
+
+
+#[test]
+fn test_webhooks() {
+    let (mut testkit, api) = create_testkit();
+    let (url, requests) = start_webhook_stub(vec![500]);
+    let (lot_tx, pub_session_key, secret_session_key, secret_key) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 18", 100);
+    testkit.create_block();
+
+    let webhook = Webhook {
+        url,
+        secret: "secret".to_owned(),
+        filter: EventFilter { lot_pub_key: Some(*lot_tx.pub_key()), seller_pub_key: None },
+        events: vec!["lot_closed".to_owned()]
+    };
+    let registered: WebhookQuery = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&webhook)
+        .post("v1/webhooks")
+        .unwrap();
+    let entries: Vec<WebhookEntry> = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/webhooks")
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].id, registered.id);
+    assert_eq!(entries[0].webhook, Webhook { secret: webhooks::REDACTED_SECRET.to_owned(), ..webhook.clone() });
+    let ftp = Webhook { url: "ftp://localhost/hook".to_owned(), ..webhook.clone() };
+    assert_matches!(
+        api.inner.private(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&ftp).post::<WebhookQuery>("v1/webhooks"),
+        Err(api::Error::BadRequest(_))
+    );
+
+    api.create_bid(lot_tx.pub_key(), &pub_session_key, 101, 1);
+    testkit.create_block();
+    let close_tx = api.close_lot(lot_tx.pub_key(), &pub_session_key, &secret_session_key, &secret_key);
+    testkit.create_block();
+
+    let (_, rejected) = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    let (headers, body) = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(rejected, body);
+    assert_eq!(headers["x-auction-signature"], webhooks::sign("secret", body.as_bytes()));
+    assert_eq!(headers["x-auction-event"], "lot_closed");
+    let event: AuctionEvent = serde_json::from_str(&body).unwrap();
+    assert_matches!(event, AuctionEvent::LotClosed { tx_hash, .. } if tx_hash == close_tx.hash());
+    assert!(requests.recv_timeout(Duration::from_millis(500)).is_err());
+
+    let height = testkit.height().0;
+    let report: RedeliverReport = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&RedeliverQuery { from_height: 1, to_height: height })
+        .post("v1/webhooks/redeliver")
+        .unwrap();
+    assert_eq!(report, RedeliverReport { events: 3, queued: 1 });
+    let (_, redelivered) = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(redelivered, body);
+    assert_matches!(
+        api.inner
+            .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&RedeliverQuery { from_height: 1, to_height: height + 1 })
+            .post::<RedeliverReport>("v1/webhooks/redeliver"),
+        Err(api::Error::BadRequest(_))
+    );
+
+    let query = WebhookQuery { id: registered.id };
+    let _: WebhookQuery = api.inner.private(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&query).post("v1/webhooks/delete").unwrap();
+    assert_matches!(
+        api.inner.private(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&query).post::<WebhookQuery>("v1/webhooks/delete"),
+        Err(api::Error::NotFound(_))
+    );
+}
+
+
+#[test]
+fn test_graphql() {
+    let (mut testkit, api) = create_testkit();
+    let token_hash = crypto::hash(&[0]);
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (lot_tx1, _, _, _) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 40", 100);
+    let (lot_tx2, _, _, _) = api.create_seller_lot(&seller_pub_key, &seller_key, &token_hash, &[], "Lot 41", 200);
+    api.create_lot(&token_hash, &[], "Lot 42", 300);
+    testkit.create_block();
+    let (bid_tx1, _) = api.create_bid(lot_tx1.pub_key(), lot_tx1.pub_session_key(), 101, 1);
+    let (bid_tx2, _) = api.create_bid(lot_tx1.pub_key(), lot_tx1.pub_session_key(), 102, 2);
+    testkit.create_block();
+
+    let query = r#"query Lot($key: String!) {
+        lot(pubKey: $key) {
+            description
+            price
+            bids(first: 1) { totalCount edges { cursor node { pubKey lotPubKey } } pageInfo { hasNextPage endCursor } }
+            seller { lots { totalCount edges { node { description } } } }
+            winner { id }
+        }
+    }"#;
+    let response = api.graphql(&json!({ "query": query, "variables": { "key": lot_tx1.pub_key() } }));
+    let lot = &response["data"]["lot"];
+    assert_eq!(lot["description"], "Lot 40");
+    assert_eq!(lot["price"], "100");
+    assert_eq!(lot["bids"]["totalCount"], 2);
+    // Connections are ordered by key.
+    let (first_key, second_key) = if bid_tx1.pub_key().to_hex() < bid_tx2.pub_key().to_hex() {
+        (bid_tx1.pub_key(), bid_tx2.pub_key())
+    } else {
+        (bid_tx2.pub_key(), bid_tx1.pub_key())
+    };
+    assert_eq!(lot["bids"]["edges"][0]["cursor"], json!(first_key));
+    assert_eq!(lot["bids"]["edges"][0]["node"]["pubKey"], json!(first_key));
+    assert_eq!(lot["bids"]["edges"][0]["node"]["lotPubKey"], json!(lot_tx1.pub_key()));
+    assert_eq!(lot["bids"]["pageInfo"], json!({ "hasNextPage": true, "endCursor": first_key }));
+    assert_eq!(lot["seller"]["lots"]["totalCount"], 2);
+    let mut descriptions: Vec<_> = lot["seller"]["lots"]["edges"].as_array().unwrap().iter()
+        .map(|edge| edge["node"]["description"].clone())
+        .collect();
+    descriptions.sort_by_key(|description| description.to_string());
+    assert_eq!(descriptions, vec![json!("Lot 40"), json!("Lot 41")]);
+    assert_eq!(lot["winner"], serde_json::Value::Null);
+
+    let query = format!(r#"{{ lot(pubKey: "{}") {{ bids(after: "{}") {{ edges {{ node {{ pubKey }} }} pageInfo {{ hasNextPage }} }} }} }}"#,
+        lot_tx1.pub_key().to_hex(), first_key.to_hex());
+    let bids = &api.graphql(&json!({ "query": query }))["data"]["lot"]["bids"];
+    assert_eq!(bids["edges"], json!([{ "node": { "pubKey": second_key } }]));
+    assert_eq!(bids["pageInfo"]["hasNextPage"], false);
+
+    let query = format!(r#"{{ lot(pubKey: "{}") {{ description }} }}"#, lot_tx2.pub_key().to_hex());
+    assert_eq!(api.graphql(&json!({ "query": query }))["data"]["lot"]["description"], "Lot 41");
+
+    let response = api.graphql(&json!({ "query": "{ lots(first: 1000) { totalCount } }" }));
+    let error: ErrorInfo = serde_json::from_str(response["errors"][0]["message"].as_str().unwrap()).unwrap();
+    assert_eq!(error.code, Error::InvalidQuery.code());
+    assert_eq!(error.context["max_page_size"], 100);
+
+    let query = "{ lots { edges { node { bids { edges { node { lot { bids { edges { node { lot { description } } } } } } } } } } } }";
+    let response = api.graphql(&json!({ "query": query }));
+    let error: ErrorInfo = serde_json::from_str(response["errors"][0]["message"].as_str().unwrap()).unwrap();
+    assert_eq!(error.context["reason"], "Query is too deep");
+    assert_eq!(response["data"], serde_json::Value::Null);
+
+    let query = "query Lots($first: Int) { lots(first: $first) { edges { node { ...Bids } } } }
+        fragment Bids on Lot { bids { edges { node { member { bids { totalCount } } } } } }";
+    let response = api.graphql(&json!({ "query": query, "variables": { "first": 100 } }));
+    let error: ErrorInfo = serde_json::from_str(response["errors"][0]["message"].as_str().unwrap()).unwrap();
+    assert_eq!(error.context["reason"], "Query is too complex");
+    let response = api.graphql(&json!({ "query": query, "variables": { "first": 1 } }));
+    assert_eq!(response["data"]["lots"]["edges"].as_array().unwrap().len(), 1);
+}
+
+
+type StubRequest = (HashMap<String, String>, String);
+
+
+#[test]
+fn test_private_api() {
+    let (mut testkit, api) = create_testkit();
+    let (lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Lot 19", 100);
+    testkit.create_block();
+    let (bid_tx, _) = api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 101, 1);
+    testkit.poll_events();
+
+    let pending: Vec<PendingTransaction> = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/mempool")
+        .unwrap();
+    let hashes: Vec<Hash> = pending.iter().map(|tx| tx.tx_hash).collect();
+    assert_eq!(hashes, vec![bid_tx.hash()]);
+    assert_matches!(
+        api.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).get::<Vec<PendingTransaction>>("v1/mempool"),
+        Err(api::Error::NotFound(_))
+    );
+    testkit.create_block();
+
+    let stats: SchemaStats = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/schema_stats")
+        .unwrap();
+    assert_eq!((stats.lots, stats.bids, stats.lot_txs), (1, 1, 2));
+
+    let mut fork = testkit.blockchain().fork();
+    AuctionSchema::new(&mut fork).lot_txs_mut(lot_tx.pub_key()).clear();
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+    let report: RebuildReport = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .post("v1/rebuild_indexes")
+        .unwrap();
+    assert_eq!(report.lot_txs, 2);
+    assert!(AuctionSchema::new(testkit.snapshot()).lot_txs(lot_tx.pub_key()).is_empty());
+    let (later_bid_tx, _) = api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 102, 2);
+    testkit.create_block();
+    let txs: Vec<LotTransaction> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotTxsQuery { lot_pub_key: *lot_tx.pub_key() })
+        .get("v1/lot_txs")
+        .unwrap();
+    let hashes: Vec<Hash> = txs.iter().map(|tx| tx.tx_hash).collect();
+    assert_eq!(hashes, vec![lot_tx.hash(), bid_tx.hash(), later_bid_tx.hash()]);
+
+    let settings: NodeSettings = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/settings")
+        .unwrap();
+    assert_eq!(settings.media.max_size, ex_auction::media::DEFAULT_MAX_MEDIA_SIZE);
+    assert_eq!(settings.webhooks, 0);
+    assert!(!settings.overridden);
+
+    let local = LocalSettings {
+        media: MediaConfig { max_size: 4, mime_types: vec!["text/plain".to_owned()] },
+        webhook_retry: RetryPolicy { attempts: 2, backoff_ms: 10 }
+    };
+    let settings: NodeSettings = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&local)
+        .post("v1/settings")
+        .unwrap();
+    assert_eq!((settings.media, settings.webhook_retry, settings.overridden), (local.media.clone(), local.webhook_retry, true));
+    let media = Media { mime_type: "text/plain".to_owned(), data: "0102030405".to_owned() };
+    let error = api.post_error("v1/media", &media);
+    assert_eq!(error.code, Error::MediaRejected.code());
+    let no_attempts = LocalSettings { webhook_retry: RetryPolicy { attempts: 0, backoff_ms: 10 }, ..local.clone() };
+    assert_matches!(
+        api.inner.private(ApiKind::Service(AUCTION_SERVICE_NAME)).query(&no_attempts).post::<NodeSettings>("v1/settings"),
+        Err(api::Error::BadRequest(_))
+    );
+
+    let settings: NodeSettings = api.inner
+        .private(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .post("v1/settings/reset")
+        .unwrap();
+    assert_eq!((settings.media, settings.overridden), (MediaConfig::default(), false));
+}
+
+
+#[test]
+fn test_service_config() {
+    let (admin_key, _) = crypto::gen_keypair();
+    let config = ServiceConfig {
+        admin_keys: vec![admin_key],
+        listing_fee: 5,
+        sale_fee_bp: 250,
+        min_bid_increment: 10,
+        max_description_length: 16,
+        default_deadline: 100,
+        vote_threshold: 0
+    };
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(AuctionService::default().with_config(config.clone()))
+        .create();
+    let api = AuctionApi { inner: testkit.api() };
+
+    let active: ServiceConfig = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .get("v1/config")
+        .unwrap();
+    assert_eq!(active, config);
+    assert!(active.is_admin(&admin_key));
+    assert_eq!(testkit.actual_configuration().services[AUCTION_SERVICE_NAME], serde_json::to_value(&config).unwrap());
+
+    let (lot_tx, _, _, _) = api.create_lot(&crypto::hash(&[0]), &[], "Sixteen chars ok", 100);
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (pub_key, key) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&pub_key, &seller_key);
+    let long_tx = TxCreateLot::new(&pub_key, &seller_pub_key, &seller_signature, &pub_key, &crypto::hash(&[0]), &[], "Seventeen chars!!", 100, 0, &key);
+    let long_tx = api.post_tx("v1/lot", &long_tx);
+    testkit.create_block();
+    assert_eq!(api.get_lot(*lot_tx.pub_key()).description(), "Sixteen chars ok");
+    assert_eq!(api.tx_error(long_tx), Error::DescriptionTooLong.with("length", 17).with("max_description_length", 16));
+}
+
+
+#[test]
+fn test_config_governance() {
+    let admins: Vec<_> = (0..3).map(|_| crypto::gen_keypair()).collect();
+    let genesis = ServiceConfig { admin_keys: admins.iter().map(|(pub_key, _)| *pub_key).collect(), ..ServiceConfig::default() };
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(AuctionService::default().with_config(genesis.clone()))
+        .create();
+    let api = AuctionApi { inner: testkit.api() };
+    let new_config = ServiceConfig { max_description_length: 8, ..genesis.clone() };
+    let new_config_json = serde_json::to_string(&new_config).unwrap();
+
+    let (outsider, outsider_key) = crypto::gen_keypair();
+    let outsider_tx = api.post_tx("v1/config/propose", &TxProposeConfig::new(&outsider, &new_config_json, 5, &outsider_key));
+    let late_tx = api.post_tx("v1/config/propose", &TxProposeConfig::new(&admins[0].0, &new_config_json, 1, &admins[0].1));
+    let propose_tx = TxProposeConfig::new(&admins[0].0, &new_config_json, 5, &admins[0].1);
+    let proposal_hash = propose_tx.hash();
+    api.post_tx("v1/config/propose", &propose_tx);
+    testkit.create_block();
+    assert_eq!(api.tx_error(outsider_tx), Error::NotAdmin.with("pub_key", outsider));
+    assert_eq!(api.tx_error(late_tx).code, Error::InvalidConfig.code());
+    let proposals: Vec<ConfigProposalInfo> = api.get_config("v1/config/proposals");
+    assert_eq!(proposals.len(), 1);
+    assert_eq!((proposals[0].votes.clone(), proposals[0].required_votes, proposals[0].accepted), (vec![admins[0].0], 2, false));
+    assert_eq!(api.get_config::<Option<ConfigProposalInfo>>("v1/config/pending"), None);
+
+    let repeated_tx = api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[0].0, &proposal_hash, &admins[0].1));
+    testkit.create_block();
+    assert_eq!(api.tx_error(repeated_tx).code, Error::AlreadyVoted.code());
+    api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[1].0, &proposal_hash, &admins[1].1));
+    testkit.create_block();
+    let pending: ConfigProposalInfo = api.get_config::<Option<_>>("v1/config/pending").unwrap();
+    assert_eq!((pending.proposal_hash, pending.config.clone(), pending.accepted), (proposal_hash, new_config.clone(), true));
+    let late_vote_tx = api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[2].0, &proposal_hash, &admins[2].1));
+    assert_eq!(api.get_config::<ServiceConfig>("v1/config"), genesis);
+
+    // Activated before block 5 is executed, so that every transaction of it sees the new config.
+    testkit.create_block();
+    assert_eq!(api.tx_error(late_vote_tx).code, Error::ProposalClosed.code());
+    assert_eq!(api.get_config::<ServiceConfig>("v1/config"), new_config);
+    assert_eq!(api.get_config::<Option<ConfigProposalInfo>>("v1/config/pending"), None);
+    let history: Vec<ConfigChange> = api.get_config("v1/config/history");
+    assert_eq!(history, vec![
+        ConfigChange { height: 0, proposal_hash: Hash::zero(), config: genesis },
+        ConfigChange { height: 5, proposal_hash, config: new_config.clone() }
+    ]);
+    let (pub_key, key) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&pub_key, &key);
+    let lot_tx = TxCreateLot::new(&pub_key, &pub_key, &seller_signature, &pub_key, &crypto::hash(&[0]), &[], "Too long", 100, 0, &key);
+    api.post_tx("v1/lot", &lot_tx);
+    let (pub_key, key) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&pub_key, &key);
+    let lot_tx = TxCreateLot::new(&pub_key, &pub_key, &seller_signature, &pub_key, &crypto::hash(&[0]), &[], "Far too long", 100, 0, &key);
+    let long_tx = api.post_tx("v1/lot", &lot_tx);
+    testkit.create_block();
+    assert_eq!(api.tx_error(long_tx).code, Error::DescriptionTooLong.code());
+
+    // A proposal accepted later supersedes the pending one, however far off its activation is.
+    let stuck_config = ServiceConfig { max_description_length: 4, ..new_config.clone() };
+    let stuck_tx = TxProposeConfig::new(&admins[0].0, &serde_json::to_string(&stuck_config).unwrap(), 1_000_000, &admins[0].1);
+    api.post_tx("v1/config/propose", &stuck_tx);
+    testkit.create_block();
+    api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[1].0, &stuck_tx.hash(), &admins[1].1));
+    testkit.create_block();
+    assert_eq!(api.get_config::<Option<ConfigProposalInfo>>("v1/config/pending").unwrap().proposal_hash, stuck_tx.hash());
+    let actual_from = testkit.height().0 + 5;
+    let fixed_config = ServiceConfig { max_description_length: 16, ..new_config.clone() };
+    let fixed_tx = TxProposeConfig::new(&admins[1].0, &serde_json::to_string(&fixed_config).unwrap(), actual_from, &admins[1].1);
+    api.post_tx("v1/config/propose", &fixed_tx);
+    testkit.create_block();
+    assert_eq!(api.get_config::<Option<ConfigProposalInfo>>("v1/config/pending").unwrap().proposal_hash, stuck_tx.hash());
+    api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[2].0, &fixed_tx.hash(), &admins[2].1));
+    testkit.create_block();
+    assert_eq!(api.get_config::<Option<ConfigProposalInfo>>("v1/config/pending").unwrap().proposal_hash, fixed_tx.hash());
+    let stuck_vote_tx = api.post_tx("v1/config/vote", &TxVoteConfig::new(&admins[2].0, &stuck_tx.hash(), &admins[2].1));
+    testkit.create_block();
+    assert_eq!(api.tx_error(stuck_vote_tx).code, Error::ProposalClosed.code());
+    assert_eq!(api.get_config::<ServiceConfig>("v1/config"), new_config);
+    testkit.create_block();
+    assert_eq!(api.get_config::<ServiceConfig>("v1/config"), fixed_config);
+    let history: Vec<ConfigChange> = api.get_config("v1/config/history");
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[2], ConfigChange { height: actual_from, proposal_hash: fixed_tx.hash(), config: fixed_config });
+}
+
+
+#[test]
+fn test_moderation() {
+    let (admin, admin_key) = crypto::gen_keypair();
+    let genesis = ServiceConfig { admin_keys: vec![admin], ..ServiceConfig::default() };
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(AuctionService::default().with_config(genesis))
+        .create();
+    let api = AuctionApi { inner: testkit.api() };
+    let token_hash = crypto::hash(&[0]);
+    let (lot_tx1, _, secret_session_key, lot_key) = api.create_lot(&token_hash, &[], "Lot 60", 100);
+    let (lot_tx2, _, _, _) = api.create_lot(&token_hash, &[], "Lot 61", 100);
+    let (lot_tx3, _, secret_session_key3, lot_key3) = api.create_lot(&token_hash, &[], "Lot 62", 100);
+    testkit.create_block();
+
+    let (outsider, outsider_key) = crypto::gen_keypair();
+    let outsider_tx = api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&outsider, lot_tx1.pub_key(), true, "Spam", &outsider_key));
+    api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx1.pub_key(), true, "Counterfeit check", &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(outsider_tx), Error::NotAdmin.with("pub_key", outsider));
+    let moderation: Option<LotModeration> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotModerationQuery { lot_pub_key: *lot_tx1.pub_key() })
+        .get("v1/lot_moderation")
+        .unwrap();
+    let moderation = moderation.unwrap();
+    assert_eq!((moderation.suspended(), moderation.delisted(), moderation.reason(), moderation.admin()), (true, false, "Counterfeit check", &admin));
+
+    let (bid_tx, _) = sealed_bid(lot_tx1.pub_key(), lot_tx1.pub_session_key(), 101, 1);
+    let suspended_bid_tx = api.post_tx("v1/bid", &bid_tx);
+    let close_tx = TxCloseLot::new(lot_tx1.pub_key(), lot_tx1.pub_session_key(), &secret_session_key.to_hex(), &lot_key);
+    let suspended_close_tx = api.post_tx("v1/close_lot", &close_tx);
+    testkit.create_block();
+    assert_eq!(api.tx_error(suspended_bid_tx).code, Error::LotSuspended.code());
+    assert_eq!(api.tx_error(suspended_close_tx).code, Error::LotSuspended.code());
+    api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx1.pub_key(), false, "Verified", &admin_key));
+    testkit.create_block();
+    let (bid_tx, _) = sealed_bid(lot_tx1.pub_key(), lot_tx1.pub_session_key(), 101, 1);
+    api.post_tx("v1/bid", &bid_tx);
+    testkit.create_block();
+    api.assert_tx_status(bid_tx.hash(), &json!({ "type": "success" }));
+
+    api.post_tx("v1/delist_lot", &TxDelistLot::new(&admin, lot_tx2.pub_key(), "Prohibited item", &admin_key));
+    testkit.create_block();
+    let relist_tx = api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx2.pub_key(), false, "Mistake", &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(relist_tx).code, Error::LotDelisted.code());
+    let query = BatchQuery { pub_keys: vec![*lot_tx1.pub_key(), *lot_tx2.pub_key()], height: None, include_delisted: false };
+    let lots: BatchResponse<Lot> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&query)
+        .post("v1/lots/batch")
+        .unwrap();
+    assert_eq!((lots.found.len(), lots.missing, lots.delisted), (1, vec![], vec![*lot_tx2.pub_key()]));
+    let query = BatchQuery { include_delisted: true, ..query };
+    let lots: BatchResponse<Lot> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&query)
+        .post("v1/lots/batch")
+        .unwrap();
+    assert_eq!((lots.found.len(), lots.delisted), (2, vec![]));
+    for &(include_delisted, expected) in &[(false, 2), (true, 3)] {
+        let lots: Vec<Lot> = api.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&LotsQuery { include_delisted, ..LotsQuery::default() })
+            .get("v1/lots")
+            .unwrap();
+        assert_eq!(lots.len(), expected);
+        assert_eq!(lots.iter().any(|lot| lot.pub_key() == lot_tx2.pub_key()), include_delisted);
+        let response = api.graphql(&json!({
+            "query": "query($all: Boolean) { lots(includeDelisted: $all) { totalCount } }",
+            "variables": { "all": include_delisted }
+        }));
+        assert_eq!(response["data"]["lots"]["totalCount"], json!(expected));
+    }
+    let first_page: Vec<Lot> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotsQuery { limit: Some(1), ..LotsQuery::default() })
+        .get("v1/lots")
+        .unwrap();
+    let second_page: Vec<Lot> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&LotsQuery { after: Some(*first_page[0].pub_key()), ..LotsQuery::default() })
+        .get("v1/lots")
+        .unwrap();
+    assert_eq!(first_page.len() + second_page.len(), 2);
+    assert!(first_page[0].pub_key().to_hex() < second_page[0].pub_key().to_hex());
+
+    // A closed lot can be suspended until it is settled.
+    let close_tx = TxCloseLot::new(lot_tx3.pub_key(), lot_tx3.pub_session_key(), &secret_session_key3.to_hex(), &lot_key3);
+    api.post_tx("v1/close_lot", &close_tx);
+    testkit.create_block();
+    api.assert_tx_status(close_tx.hash(), &json!({ "type": "success" }));
+    let suspend_tx = api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx3.pub_key(), true, "Disputed", &admin_key));
+    testkit.create_block();
+    api.assert_tx_status(suspend_tx, &json!({ "type": "success" }));
+    let response = api.graphql(&json!({ "query": format!("{{ lot(pubKey: \"{}\") {{ opened suspended }} }}", lot_tx3.pub_key().to_hex()) }));
+    assert_eq!(response["data"]["lot"], json!({ "opened": false, "suspended": true }));
+    let response = api.graphql(&json!({ "query": format!("{{ lot(pubKey: \"{}\") {{ suspended delisted }} }}", lot_tx2.pub_key().to_hex()) }));
+    assert_eq!(response["data"]["lot"], json!({ "suspended": false, "delisted": true }));
+}
+
+
+#[test]
+fn test_reputation() {
+    let (admin, admin_key) = crypto::gen_keypair();
+    let genesis = ServiceConfig { admin_keys: vec![admin], ..ServiceConfig::default() };
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(AuctionService::default().with_config(genesis))
+        .create();
+    let api = AuctionApi { inner: testkit.api() };
+    let token_hash = crypto::hash(&[0]);
+    let (seller, seller_key) = crypto::gen_keypair();
+    let (bidder, bidder_key) = crypto::gen_keypair();
+    let (lot_tx, _, secret_session_key, lot_key) = api.create_seller_lot(&seller, &seller_key, &token_hash, &[], "Lot 70", 100);
+    let (unsold_lot_tx, _, unsold_secret_session_key, unsold_lot_key) = api.create_seller_lot(&seller, &seller_key, &token_hash, &[], "Lot 73", 100);
+    testkit.create_block();
+    let winning_bid_tx = member_bid(&bidder, &bidder_key, lot_tx.pub_key(), lot_tx.pub_session_key(), 150, 7);
+    api.post_tx("v1/bid", &winning_bid_tx);
+    api.create_bid(lot_tx.pub_key(), lot_tx.pub_session_key(), 120, 8);
+    testkit.create_block();
+    let (_, wrong_secret_session_key) = crypto::gen_keypair();
+    let wrong_close_tx = api.close_lot(lot_tx.pub_key(), lot_tx.pub_session_key(), &wrong_secret_session_key, &lot_key);
+    testkit.create_block();
+    assert_eq!(api.tx_error(wrong_close_tx.hash()).code, Error::SessionKeyMismatch.code());
+    api.close_lot(lot_tx.pub_key(), lot_tx.pub_session_key(), &secret_session_key, &lot_key);
+    api.close_lot(unsold_lot_tx.pub_key(), unsold_lot_tx.pub_session_key(), &unsold_secret_session_key, &unsold_lot_key);
+    testkit.create_block();
+    let lot = api.get_lot(*lot_tx.pub_key());
+    assert_eq!((lot.opened(), lot.winner_member_id(), lot.price()), (false, 7, 150));
+    let bid = api.get_bid(*winning_bid_tx.pub_key());
+    assert_eq!((bid.encrypted(), bid.member_id(), bid.sum()), (false, 7, 150));
+    let unsold_tx = api.post_tx("v1/settle_lot", &TxSettleLot::new(&admin, unsold_lot_tx.pub_key(), SettlementOutcome::NonPayment as u8, &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(unsold_tx).code, Error::LotNotSold.code());
+
+    // A suspended lot cannot be settled until the suspension is lifted.
+    api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx.pub_key(), true, "Disputed", &admin_key));
+    testkit.create_block();
+    let suspended_tx = api.post_tx("v1/settle_lot", &TxSettleLot::new(&admin, lot_tx.pub_key(), SettlementOutcome::NonPayment as u8, &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(suspended_tx).code, Error::LotSuspended.code());
+    api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx.pub_key(), false, "Resolved", &admin_key));
+    testkit.create_block();
+    let invalid_tx = TxSettleLot::new(&admin, lot_tx.pub_key(), 7, &admin_key);
+    assert_eq!(api.post_error("v1/settle_lot", &invalid_tx), Error::InvalidOutcome.with("outcome", 7));
+    api.post_tx("v1/settle_lot", &TxSettleLot::new(&admin, lot_tx.pub_key(), SettlementOutcome::Completed as u8, &admin_key));
+    testkit.create_block();
+    let repeated_tx = TxSettleLot::new(&admin, lot_tx.pub_key(), SettlementOutcome::FailedDelivery as u8, &admin_key);
+    let repeated_tx = api.post_tx("v1/settle_lot", &repeated_tx);
+    let late_suspend_tx = api.post_tx("v1/suspend_lot", &TxSuspendLot::new(&admin, lot_tx.pub_key(), true, "Late dispute", &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(repeated_tx).code, Error::LotAlreadySettled.code());
+    assert_eq!(api.tx_error(late_suspend_tx).code, Error::LotAlreadySettled.code());
+
+    // A closed lot takes no more bids and cannot be closed again, even after settlement. Submission
+    // rejects both, and so does execution when they bypass the API.
+    let late_bid_tx = member_bid(&bidder, &bidder_key, lot_tx.pub_key(), lot_tx.pub_session_key(), 500, 7);
+    // Upper-case hex opens the same key but keeps the transaction distinct from the first close.
+    let reclose_tx = TxCloseLot::new(lot_tx.pub_key(), lot_tx.pub_session_key(), &secret_session_key.to_hex().to_uppercase(), &lot_key);
+    let closed = Error::LotClosed.with("lot_pub_key", lot_tx.pub_key());
+    assert_eq!(api.post_error("v1/bid", &late_bid_tx), closed);
+    assert_eq!(api.post_error("v1/close_lot", &reclose_tx), closed);
+    testkit.create_block_with_transaction(late_bid_tx.clone());
+    testkit.create_block_with_transaction(reclose_tx.clone());
+    assert_eq!(api.tx_error(late_bid_tx.hash()), closed);
+    assert_eq!(api.tx_error(reclose_tx.hash()), closed);
+    let lot = api.get_lot(*lot_tx.pub_key());
+    assert_eq!((lot.opened(), lot.winner_member_id(), lot.price()), (false, 7, 150));
+    let settlement: Option<Settlement> = api.inner
+        .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+        .query(&SettlementQuery { lot_pub_key: *lot_tx.pub_key() })
+        .get("v1/settlement")
+        .unwrap();
+    assert_eq!(settlement.map(|settlement| (*settlement.winner(), settlement.outcome())), Some((bidder, 0)));
+    assert_eq!(api.get_reputation(seller).reputation, Reputation::new(1, 0, 0, 0));
+    let reputation = api.get_reputation(bidder);
+    assert_eq!((reputation.score, reputation.reputation, reputation.ban), (1, Reputation::new(0, 1, 0, 0), None));
+
+    let (pub_key, key) = crypto::gen_keypair();
+    let (pub_session_key, _) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&pub_key, &seller_key);
+    let picky_tx = TxCreateLot::new(&pub_key, &seller, &seller_signature, &pub_session_key, &token_hash, &[], "Lot 71", 100, 2, &key);
+    api.post_tx("v1/lot", &picky_tx);
+    testkit.create_block();
+    let bid_tx = api.post_tx("v1/bid", &member_bid(&bidder, &bidder_key, &pub_key, &pub_session_key, 150, 7));
+    testkit.create_block();
+    assert_eq!(api.tx_error(bid_tx), Error::ReputationTooLow.with("score", 1).with("min_reputation", 2));
+
+    let (outsider, outsider_key) = crypto::gen_keypair();
+    let outsider_tx = api.post_tx("v1/ban_member", &TxBanMember::new(&outsider, &bidder, true, "Spam", &outsider_key));
+    api.post_tx("v1/ban_member", &TxBanMember::new(&admin, &bidder, true, "Shill bidding", &admin_key));
+    api.post_tx("v1/ban_member", &TxBanMember::new(&admin, &seller, true, "Counterfeits", &admin_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(outsider_tx), Error::NotAdmin.with("pub_key", outsider));
+    let bid_tx = api.post_tx("v1/bid", &member_bid(&bidder, &bidder_key, &pub_key, &pub_session_key, 151, 7));
+    let ban = api.get_reputation(bidder).ban.unwrap();
+    assert_eq!((ban.banned(), ban.reason(), ban.admin()), (true, "Shill bidding", &admin));
+    let (lot_pub_key, lot_key) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&lot_pub_key, &seller_key);
+    let lot_tx = TxCreateLot::new(&lot_pub_key, &seller, &seller_signature, &pub_session_key, &token_hash, &[], "Lot 72", 100, 0, &lot_key);
+    let lot_tx = api.post_tx("v1/lot", &lot_tx);
+    testkit.create_block();
+    assert_eq!(api.tx_error(bid_tx), Error::MemberBanned.with("pub_key", bidder));
+    assert_eq!(api.tx_error(lot_tx), Error::MemberBanned.with("pub_key", seller));
+}
+
+
+#[test]
+fn test_ratings() {
+    let (admin, admin_key) = crypto::gen_keypair();
+    let genesis = ServiceConfig { admin_keys: vec![admin], ..ServiceConfig::default() };
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(AuctionService::default().with_config(genesis))
+        .create();
+    let api = AuctionApi { inner: testkit.api() };
+    let (seller, seller_key) = crypto::gen_keypair();
+    let (winner, winner_key) = crypto::gen_keypair();
+    let mut lots = Vec::new();
+    for description in &["Lot 80", "Lot 81"] {
+        let (lot_tx, _, secret_session_key, lot_key) = api.create_seller_lot(&seller, &seller_key, &crypto::hash(&[0]), &[], description, 100);
+        testkit.create_block();
+        api.post_tx("v1/bid", &member_bid(&winner, &winner_key, lot_tx.pub_key(), lot_tx.pub_session_key(), 150, 7));
+        testkit.create_block();
+        api.close_lot(lot_tx.pub_key(), lot_tx.pub_session_key(), &secret_session_key, &lot_key);
+        testkit.create_block();
+        lots.push(*lot_tx.pub_key());
+    }
+
+    let early_tx = api.post_tx("v1/rate", &TxRateCounterparty::new(&winner, &lots[0], 5, "Early", &winner_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(early_tx).code, Error::LotNotSettled.code());
+    for lot_pub_key in &lots {
+        api.post_tx("v1/settle_lot", &TxSettleLot::new(&admin, lot_pub_key, SettlementOutcome::Completed as u8, &admin_key));
+    }
+    testkit.create_block();
+    let (outsider, outsider_key) = crypto::gen_keypair();
+    let outsider_tx = api.post_tx("v1/rate", &TxRateCounterparty::new(&outsider, &lots[0], 1, "Never got it", &outsider_key));
+    let invalid_tx = TxRateCounterparty::new(&winner, &lots[0], 6, "Great", &winner_key);
+    assert_eq!(api.post_error("v1/rate", &invalid_tx).code, Error::InvalidRating.code());
+    testkit.create_block();
+    assert_eq!(api.tx_error(outsider_tx).code, Error::NotLotParty.code());
+
+    api.post_tx("v1/rate", &TxRateCounterparty::new(&winner, &lots[0], 5, "Great", &winner_key));
+    api.post_tx("v1/rate", &TxRateCounterparty::new(&winner, &lots[1], 2, "Slow shipping", &winner_key));
+    api.post_tx("v1/rate", &TxRateCounterparty::new(&seller, &lots[0], 4, "Paid promptly", &seller_key));
+    testkit.create_block();
+    let repeated_tx = api.post_tx("v1/rate", &TxRateCounterparty::new(&seller, &lots[0], 1, "Changed my mind", &seller_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(repeated_tx).code, Error::AlreadyRated.code());
+
+    // A seller who won their own lot is both parties and cannot rate itself.
+    let (lot_tx, _, secret_session_key, lot_key) = api.create_seller_lot(&seller, &seller_key, &crypto::hash(&[0]), &[], "Lot 82", 100);
+    testkit.create_block();
+    api.post_tx("v1/bid", &member_bid(&seller, &seller_key, lot_tx.pub_key(), lot_tx.pub_session_key(), 150, 8));
+    testkit.create_block();
+    api.close_lot(lot_tx.pub_key(), lot_tx.pub_session_key(), &secret_session_key, &lot_key);
+    testkit.create_block();
+    api.post_tx("v1/settle_lot", &TxSettleLot::new(&admin, lot_tx.pub_key(), SettlementOutcome::Completed as u8, &admin_key));
+    testkit.create_block();
+    let self_tx = api.post_tx("v1/rate", &TxRateCounterparty::new(&seller, lot_tx.pub_key(), 5, "Great", &seller_key));
+    testkit.create_block();
+    assert_eq!(api.tx_error(self_tx), Error::SelfRating.with("pub_key", seller).with("lot_pub_key", lot_tx.pub_key()));
+
+    let ratings = api.get_ratings(seller);
+    assert_eq!((ratings.count, ratings.average), (2, Some(3.5)));
+    // Transactions of one block are ordered by hash, not by submission.
+    let mut reviews: Vec<(&PublicKey, &str)> = ratings.ratings.iter().map(|rating| (rating.from(), rating.review())).collect();
+    reviews.sort_by_key(|&(_, review)| review);
+    assert_eq!(reviews, vec![(&winner, "Great"), (&winner, "Slow shipping")]);
+    let ratings = api.get_ratings(winner);
+    assert_eq!((ratings.count, ratings.average), (1, Some(4.0)));
+    assert_eq!((ratings.ratings[0].lot_pub_key(), ratings.ratings[0].to()), (&lots[0], &winner));
+    assert_eq!(api.get_ratings(outsider).average, None);
+    let response = api.graphql(&json!({ "query": format!("{{ seller(pubKey: \"{}\") {{ averageRating }} }}", seller.to_hex()) }));
+    assert_eq!(response["data"]["seller"]["averageRating"], json!(3.5));
+}
+
+
+// Answers with the given statuses in turn, then with 200, and reports every request.
+fn start_webhook_stub(statuses: Vec<u16>) -> (String, mpsc::Receiver<StubRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut statuses = statuses.into_iter();
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut headers = HashMap::new();
+            let mut body = Vec::new();
+            {
+                let mut reader = BufReader::new(&mut stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let header = line.trim_end();
+                    if header.is_empty() {
+                        break
+                    }
+                    if let Some(colon) = header.find(':') {
+                        headers.insert(header[..colon].to_lowercase(), header[colon + 1..].trim().to_owned());
+                    }
+                }
+                let length = headers.get("content-length").map_or(0, |length| length.parse().unwrap());
+                body.resize(length, 0);
+                reader.read_exact(&mut body).unwrap();
+            }
+            let status = statuses.next().unwrap_or(200);
+            write!(stream, "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            if sender.send((headers, String::from_utf8(body).unwrap())).is_err() {
+                return
+            }
+        }
+    });
+    (url, receiver)
+}
+
+
+fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+    match *value {
+        serde_json::Value::Object(ref map) => {
+            if let Some(serde_json::Value::String(reference)) = map.get("$ref") {
+                refs.push(reference.clone());
+            }
+            map.values().for_each(|value| collect_refs(value, refs));
+        },
+        serde_json::Value::Array(ref values) => values.iter().for_each(|value| collect_refs(value, refs)),
+        _ => ()
+    }
+}
+
+
 fn create_testkit() -> (TestKit, AuctionApi) {
     let (pub_session_key, secret_session_key) = exonum::crypto::gen_keypair();
     let testkit = TestKitBuilder::validator()
-        .with_service(AuctionService)
+        .with_service(AuctionService::default())
         .create();
     let api = AuctionApi {
         inner: testkit.api(),
@@ -111,21 +1337,52 @@ fn create_testkit() -> (TestKit, AuctionApi) {
 }
 
 
+// `TestKitApi` hides its server address, so WebSocket tests serve the public API themselves.
+fn create_ws_server(testkit: &TestKit) -> TestServer {
+    let aggregator = ApiAggregator::new(testkit.blockchain().clone(), SharedNodeState::new(10_000));
+    TestServer::with_factory(move || {
+        App::with_state(ServiceApiState::new(aggregator.blockchain().clone()))
+            .scope("public/api", |scope| aggregator.extend_backend(ApiAccess::Public, scope))
+    })
+}
+
+
+// Placed by a fresh member key, which is returned.
+fn sealed_bid(lot_pub_key: &PublicKey, pub_session_key: &PublicKey, sum: u64, member_id: u64) -> (TxCreateBid, SecretKey) {
+    let (pubkey, key) = crypto::gen_keypair();
+    (member_bid(&pubkey, &key, lot_pub_key, pub_session_key, sum, member_id), key)
+}
+
+
+fn member_bid(member_pub_key: &PublicKey, member_key: &SecretKey, lot_pub_key: &PublicKey, pub_session_key: &PublicKey, sum: u64, member_id: u64) -> TxCreateBid {
+    let (pubkey, key) = crypto::gen_keypair();
+    let bid = Bid::new(&pubkey, lot_pub_key, member_id, sum, false, vec![]).encrypt(pub_session_key);
+    let bid_signature = TxCreateBid::sign_member_key(member_pub_key, &key);
+    TxCreateBid::new(&pubkey, lot_pub_key, member_pub_key, &bid_signature, bid.data(), member_key)
+}
+
+
 struct AuctionApi {
     pub inner: TestKitApi
 }
 
 
 impl AuctionApi {
-    fn create_lot(&self, token_hash: &Hash, description: &str, price: u64) -> (TxCreateLot, PublicKey, SecretKey, SecretKey) {
+    fn create_lot(&self, token_hash: &Hash, media_hashes: &[Hash], description: &str, price: u64) -> (TxCreateLot, PublicKey, SecretKey, SecretKey) {
+        let (seller_pub_key, seller_key) = crypto::gen_keypair();
+        self.create_seller_lot(&seller_pub_key, &seller_key, token_hash, media_hashes, description, price)
+    }
+
+    fn create_seller_lot(&self, seller_pub_key: &PublicKey, seller_key: &SecretKey, token_hash: &Hash, media_hashes: &[Hash], description: &str, price: u64) -> (TxCreateLot, PublicKey, SecretKey, SecretKey) {
         let (pubkey, key) = crypto::gen_keypair();
         let (pub_session_key, secret_session_key) = crypto::gen_keypair();
-        let tx = TxCreateLot::new(&pubkey, &pub_session_key, token_hash, description, price, &key);
+        let seller_signature = TxCreateLot::sign_lot_key(&pubkey, seller_key);
+        let tx = TxCreateLot::new(&pubkey, seller_pub_key, &seller_signature, &pub_session_key, token_hash, media_hashes, description, price, 0, &key);
 
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
             .query(&tx)
-            .post("lot")
+            .post("v1/lot")
             .unwrap();
         assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
         (tx, pub_session_key, secret_session_key, key)
@@ -136,27 +1393,86 @@ impl AuctionApi {
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
             .query(&tx)
-            .post("close_lot")
+            .post("v1/close_lot")
             .unwrap();
         assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
         tx
     }
 
     fn create_bid(&self, lot_pub_key: &PublicKey, pub_session_key: &PublicKey, sum: u64, member_id: u64) -> (TxCreateBid, SecretKey) {
-        let (pubkey, key) = crypto::gen_keypair();
-
-        let bid = Bid::new(&pubkey, lot_pub_key, member_id, sum, false, vec![]); //.encrypt(pub_session_key);
-        let tx = TxCreateBid::new(&pubkey, lot_pub_key, bid.data(), &key);
+        let (tx, key) = sealed_bid(lot_pub_key, pub_session_key, sum, member_id);
 
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
             .query(&tx)
-            .post("bid")
+            .post("v1/bid")
             .unwrap();
         assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
         (tx, key)
     }
 
+    fn get_reputation(&self, pub_key: PublicKey) -> MemberReputation {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&ReputationQuery { pub_key })
+            .get("v1/reputation")
+            .unwrap()
+    }
+
+    fn get_ratings(&self, pub_key: PublicKey) -> MemberRatings {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&RatingsQuery { pub_key })
+            .get("v1/ratings")
+            .unwrap()
+    }
+
+    fn get_tx_status(&self, tx_hash: Hash) -> api::Result<TransactionStatus> {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&TxStatusQuery { tx_hash })
+            .get("v1/tx_status")
+    }
+
+    fn post_tx<T: serde::Serialize>(&self, endpoint: &str, tx: &T) -> Hash {
+        let response: TransactionResponse = self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(tx)
+            .post(endpoint)
+            .unwrap();
+        response.tx_hash
+    }
+
+    // Only stateless failures, such as a bad signature, are rejected before the pool.
+    fn post_error<T: serde::Serialize>(&self, endpoint: &str, tx: &T) -> ErrorInfo {
+        match self.inner.public(ApiKind::Service(AUCTION_SERVICE_NAME)).query(tx).post::<TransactionResponse>(endpoint) {
+            Err(api::Error::BadRequest(body)) | Err(api::Error::NotFound(body)) => serde_json::from_str(&body).unwrap(),
+            other => panic!("Unexpected response: {:?}", other)
+        }
+    }
+
+    // The error a committed transaction failed with.
+    fn tx_error(&self, tx_hash: Hash) -> ErrorInfo {
+        let status = self.get_tx_status(tx_hash).unwrap();
+        assert_eq!(status.status, TxState::Failed);
+        status.error.unwrap()
+    }
+
+    fn get_config<T: serde::de::DeserializeOwned + 'static>(&self, endpoint: &str) -> T {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .get(endpoint)
+            .unwrap()
+    }
+
+    fn graphql(&self, request: &serde_json::Value) -> serde_json::Value {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(request)
+            .post("v1/graphql")
+            .unwrap()
+    }
+
     fn assert_tx_status(&self, tx_hash: Hash, expected_status: &serde_json::Value) {
         let info: serde_json::Value = self.inner
             .public(ApiKind::Explorer)
@@ -175,24 +1491,47 @@ impl AuctionApi {
     fn get_lot(&self, pub_key: PublicKey) -> Lot {
         self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
-            .query(&LotQuery { pub_key })
-            .get("lot")
+            .query(&LotQuery { pub_key, height: None })
+            .get("v1/lot")
             .unwrap()
     }
 
     fn get_bid(&self, pub_key: PublicKey) -> Bid {
         self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
-            .query(&BidQuery { pub_key })
-            .get("bid")
+            .query(&BidQuery { pub_key, height: None })
+            .get("v1/bid")
+            .unwrap()
+    }
+
+    fn post_media(&self, media: &Media) -> MediaResponse {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(media)
+            .post("v1/media")
+            .unwrap()
+    }
+
+    fn get_media(&self, hash: Hash) -> api::Result<Media> {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&MediaQuery { hash })
+            .get("v1/media")
+    }
+
+    fn get_stats(&self, query: StatsQuery) -> StatsReport {
+        self.inner
+            .public(ApiKind::Service(AUCTION_SERVICE_NAME))
+            .query(&query)
+            .get("v1/stats")
             .unwrap()
     }
 
     fn get_lot_bids(&self, lot_pub_key: PublicKey) -> Vec<Bid> {
         self.inner
             .public(ApiKind::Service(AUCTION_SERVICE_NAME))
-            .query(&LotBidsQuery { lot_pub_key })
-            .get("lot_bids")
+            .query(&LotBidsQuery { lot_pub_key, height: None })
+            .get("v1/lot_bids")
             .unwrap()
     }
 }
\ No newline at end of file
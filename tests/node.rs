@@ -0,0 +1,310 @@
+extern crate exonum;
+extern crate ex_auction;
+extern crate reqwest;
+extern crate serde;
+extern crate serde_json;
+extern crate tempdir;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use reqwest::StatusCode;
+use exonum::crypto::{self, CryptoHash, Hash, PublicKey};
+use exonum::encoding::serialize::FromHex;
+use exonum::helpers::config::ConfigFile;
+use exonum::node::{ApiSender, ExternalMessage, Node, NodeConfig};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tempdir::TempDir;
+
+use ex_auction::api::{TransactionResponse, TransactionStatus, TxState};
+use ex_auction::client::{self, AuctionClient, ClientError, KeyPair};
+use ex_auction::config::{self, AuctionConfig, NodeKeys, NodeTemplate, Storage};
+use ex_auction::errors::Error;
+use ex_auction::schema::{Bid, Lot, ServiceConfig};
+use ex_auction::transactions::TxCreateLot;
+use ex_auction::AUCTION_SERVICE_NAME;
+
+
+#[test]
+fn test_node_config_file() {
+    let dir = TempDir::new("ex_auction").unwrap();
+    let path = dir.path().join("node.toml");
+    let keys = NodeKeys::generate();
+    let mut template = NodeTemplate::new(keys.clone());
+    template.public_api_address = "127.0.0.1:8200".parse().unwrap();
+    template.auction.media.max_size = 1024;
+    template.auction.webhook_retry.attempts = 2;
+    let genesis = ServiceConfig {
+        admin_keys: vec![keys.service_public_key],
+        listing_fee: 5,
+        sale_fee_bp: 250,
+        min_bid_increment: 10,
+        max_description_length: 512,
+        default_deadline: 100,
+        vote_threshold: 1
+    };
+    template.auction.genesis = genesis.clone();
+    ConfigFile::save(&template.build().unwrap(), &path).unwrap();
+
+    let mut config: NodeConfig = ConfigFile::load(&path).unwrap();
+    assert_eq!(config.consensus_public_key, keys.consensus_public_key);
+    assert_eq!(config.service_secret_key, keys.service_secret_key);
+    assert_eq!(config.genesis.validator_keys, vec![keys.validator_keys()]);
+    assert_eq!(config.api.public_api_address, Some("127.0.0.1:8200".parse().unwrap()));
+    let auction = AuctionConfig::from_node_config(&config).unwrap();
+    assert_eq!(auction.media.max_size, 1024);
+    assert_eq!(auction.webhook_retry.attempts, 2);
+    assert_eq!(auction.genesis, genesis);
+
+    config.services_configs.remove(AUCTION_SERVICE_NAME);
+    assert_eq!(AuctionConfig::from_node_config(&config).unwrap().webhook_retry.attempts, AuctionConfig::default().webhook_retry.attempts);
+}
+
+
+#[test]
+fn test_restart() {
+    let dir = TempDir::new("ex_auction").unwrap();
+    let storage = Storage::RocksDb(dir.path().join("db"));
+    let mut template = NodeTemplate::new(NodeKeys::generate());
+    template.peer_address = "127.0.0.1:28400".parse().unwrap();
+    template.public_api_address = "127.0.0.1:28401".parse().unwrap();
+    template.private_api_address = "127.0.0.1:28402".parse().unwrap();
+    let config = template.build().unwrap();
+    let url = format!("http://127.0.0.1:28401/api/services/{}/v1", AUCTION_SERVICE_NAME);
+
+    let (sender, node) = start_node(config.clone(), &storage);
+    let (lot_pub_key, lot_key) = crypto::gen_keypair();
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (pub_session_key, _) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&lot_pub_key, &seller_key);
+    let tx = TxCreateLot::new(&lot_pub_key, &seller_pub_key, &seller_signature, &pub_session_key, &crypto::hash(&[0]), &[], "Lot 50", 100, 0, &lot_key);
+    let response: TransactionResponse = poll(|| post_json(&format!("{}/lot", url), &tx));
+    assert_eq!(response.tx_hash, tx.hash());
+    poll(|| {
+        let status: TransactionStatus = get_json(&format!("{}/tx_status?tx_hash={}", url, tx.hash().to_hex()))?;
+        if status.status == TxState::Committed { Some(()) } else { None }
+    });
+    stop_node(&sender, node);
+
+    let (sender, node) = start_node(config, &storage);
+    let lot: Lot = poll(|| get_json(&format!("{}/lot?pub_key={}", url, lot_pub_key.to_hex())));
+    assert_eq!(lot.description(), "Lot 50");
+    assert_eq!(lot.seller_pub_key(), &seller_pub_key);
+    stop_node(&sender, node);
+}
+
+
+#[test]
+fn test_client() {
+    let mut template = NodeTemplate::new(NodeKeys::generate());
+    template.peer_address = "127.0.0.1:28600".parse().unwrap();
+    template.public_api_address = "127.0.0.1:28601".parse().unwrap();
+    template.private_api_address = "127.0.0.1:28602".parse().unwrap();
+    let (sender, node) = start_node(template.build().unwrap(), &Storage::Memory);
+    let client = AuctionClient::new("http://127.0.0.1:28601").with_private_api("http://127.0.0.1:28602/");
+    poll(|| client.spec().ok());
+    let timeout = Duration::from_secs(30);
+
+    let seller = KeyPair::generate();
+    let (lot_tx, lot_keys) = client::create_lot(&seller, &crypto::hash(&[0]), &[], "Lot 52", 100, 0);
+    let tx_hash = client.submit_lot(&lot_tx).unwrap();
+    assert_eq!(tx_hash, lot_tx.hash());
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+
+    let lot = client.lot(lot_keys.lot.public_key, None).unwrap();
+    assert_eq!(lot.seller_pub_key(), &seller.public_key);
+    let (bid_tx, bid_keys) = client::create_bid(&KeyPair::generate(), &lot, 7, 150);
+    assert!(client.dry_run(&bid_tx).unwrap().success);
+    let tx_hash = client.submit_bid(&bid_tx).unwrap();
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+    let bids = client.lot_bids(*lot.pub_key(), None).unwrap();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].pub_key(), &bid_keys.public_key);
+    assert!(bids[0].encrypted());
+
+    let tx_hash = client.submit_close_lot(&client::close_lot(&lot_keys)).unwrap();
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+    assert!(!client.lot(*lot.pub_key(), None).unwrap().opened());
+    assert_eq!(client.schema_stats().unwrap().lots, 1);
+
+    let (unknown_key, _) = crypto::gen_keypair();
+    match client.lot(unknown_key, None) {
+        Err(ClientError::Api(StatusCode::NotFound, info)) => assert_eq!(info.code, Error::LotNotFound.code()),
+        other => panic!("Unexpected response: {:?}", other)
+    }
+    match AuctionClient::new("http://127.0.0.1:28601").mempool() {
+        Err(ClientError::NoPrivateApi) => {},
+        other => panic!("Unexpected response: {:?}", other)
+    }
+
+    // Its keep-alive connections would hold up node shutdown.
+    drop(client);
+    stop_node(&sender, node);
+}
+
+
+#[test]
+fn test_cli() {
+    let mut template = NodeTemplate::new(NodeKeys::generate());
+    template.peer_address = "127.0.0.1:28700".parse().unwrap();
+    template.public_api_address = "127.0.0.1:28701".parse().unwrap();
+    template.private_api_address = "127.0.0.1:28702".parse().unwrap();
+    let (sender, node) = start_node(template.build().unwrap(), &Storage::Memory);
+    let client = AuctionClient::new("http://127.0.0.1:28701");
+    poll(|| client.spec().ok());
+    let timeout = Duration::from_secs(30);
+    let dir = TempDir::new("ex_auction").unwrap();
+    let wallet = dir.path().join("wallet.toml");
+
+    let seller_pub_key = cli(&wallet, &["keygen", "seller"]);
+    cli(&wallet, &["keygen", "bidder"]);
+    assert!(cli_error(&wallet, &["keygen", "seller"]).contains("already exists"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&wallet).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    let output = cli(&wallet, &["create-lot", "--seller", "seller", "--description", "Lot 54", "--price", "100"]);
+    let (lot_pub_key, tx_hash) = submitted(&output);
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+    let lot = client.lot(lot_pub_key, None).unwrap();
+    assert_eq!(lot.description(), "Lot 54");
+    assert_eq!(lot.seller_pub_key().to_hex(), seller_pub_key);
+
+    let output = cli(&wallet, &["bid", "--lot", &lot_pub_key.to_hex(), "--bidder", "bidder", "--sum", "150", "--member-id", "7"]);
+    let (bid_pub_key, tx_hash) = submitted(&output);
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+    let bids = client.lot_bids(lot_pub_key, None).unwrap();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].pub_key(), &bid_pub_key);
+
+    let keys = cli(&wallet, &["keys"]);
+    assert!(keys.contains(&format!("seller\t{}", seller_pub_key)));
+    assert!(keys.contains(&format!("lot\t{}", lot_pub_key.to_hex())));
+    assert!(keys.contains(&format!("bid\t{}", bid_pub_key.to_hex())));
+
+    let output = cli(&wallet, &["close-lot", "--lot", &lot_pub_key.to_hex()]);
+    let tx_hash = Hash::from_hex(output.split_whitespace().last().unwrap()).unwrap();
+    assert_eq!(client.wait_for_status(tx_hash, timeout).unwrap().status, TxState::Committed);
+    let lot: Lot = serde_json::from_str(&cli(&wallet, &["lot", &lot_pub_key.to_hex()])).unwrap();
+    assert_eq!((lot.opened(), lot.winner_member_id(), lot.price()), (false, 7, 150));
+    let bid: Bid = serde_json::from_str(&cli(&wallet, &["show-bid", &bid_pub_key.to_hex()])).unwrap();
+    assert_eq!((bid.encrypted(), bid.member_id(), bid.sum()), (false, 7, 150));
+
+    drop(client);
+    stop_node(&sender, node);
+}
+
+
+#[test]
+fn test_testnet() {
+    let configs = config::testnet(2, 28500, 28510).unwrap();
+    assert_eq!(configs.len(), 2);
+    assert_eq!(configs[0].genesis.validator_keys, configs[1].genesis.validator_keys);
+    assert_eq!(configs[0].genesis.validator_keys.len(), 2);
+    assert_eq!(configs[0].connect_list.peers.len(), 1);
+    assert_eq!(configs[0].connect_list.peers[0].address, configs[1].listen_address);
+    assert_eq!(configs[0].connect_list.peers[0].public_key, configs[1].consensus_public_key);
+    assert_eq!(configs[1].api.public_api_address, Some("127.0.0.1:28511".parse().unwrap()));
+    assert_eq!(configs[1].api.private_api_address, Some("127.0.0.1:28513".parse().unwrap()));
+
+    let nodes: Vec<_> = configs.into_iter().map(|config| start_node(config, &Storage::Memory)).collect();
+    let url = |port: u16| format!("http://127.0.0.1:{}/api/services/{}/v1", port, AUCTION_SERVICE_NAME);
+    let (lot_pub_key, lot_key) = crypto::gen_keypair();
+    let (seller_pub_key, seller_key) = crypto::gen_keypair();
+    let (pub_session_key, _) = crypto::gen_keypair();
+    let seller_signature = TxCreateLot::sign_lot_key(&lot_pub_key, &seller_key);
+    let tx = TxCreateLot::new(&lot_pub_key, &seller_pub_key, &seller_signature, &pub_session_key, &crypto::hash(&[0]), &[], "Lot 51", 100, 0, &lot_key);
+    let _: TransactionResponse = poll(|| post_json(&format!("{}/lot", url(28510)), &tx));
+
+    // The lot is committed only once both validators agree on the block.
+    let lot: Lot = poll(|| get_json(&format!("{}/lot?pub_key={}", url(28511), lot_pub_key.to_hex())));
+    assert_eq!(lot.description(), "Lot 51");
+    for (sender, node) in nodes {
+        stop_node(&sender, node);
+    }
+}
+
+fn start_node(config: NodeConfig, storage: &Storage) -> (ApiSender, JoinHandle<()>) {
+    // The previous node releases the database lock only once its API threads wind down.
+    let database = poll(|| storage.open(&config.database).ok());
+    let service = AuctionConfig::from_node_config(&config).unwrap().service();
+    let (sender, receiver) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let node = Node::new(database, vec![Box::new(service)], config, None);
+        sender.send(node.channel()).unwrap();
+        node.run().unwrap();
+    });
+    (receiver.recv().unwrap(), handle)
+}
+
+
+fn stop_node(sender: &ApiSender, node: JoinHandle<()>) {
+    sender.send_external_message(ExternalMessage::Shutdown).unwrap();
+    node.join().unwrap();
+}
+
+
+fn poll<T, F: FnMut() -> Option<T>>(mut attempt: F) -> T {
+    for _ in 0..300 {
+        if let Some(value) = attempt() {
+            return value
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("Node did not respond in time");
+}
+
+
+// A client per request, so that no keep-alive connection holds up node shutdown.
+fn get_json<T: DeserializeOwned>(url: &str) -> Option<T> {
+    reqwest::Client::new().get(url).send().ok()?.json().ok()
+}
+
+
+fn post_json<B: Serialize, T: DeserializeOwned>(url: &str, body: &B) -> Option<T> {
+    reqwest::Client::new().post(url).json(body).send().ok()?.json().ok()
+}
+
+
+// Cargo builds the binaries next to the directory holding the test executables.
+fn run_cli(wallet: &Path, args: &[&str]) -> Output {
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    Command::new(path.join("ex_auction-cli"))
+        .arg("--wallet").arg(wallet)
+        .arg("--node").arg("http://127.0.0.1:28701")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+
+fn cli(wallet: &Path, args: &[&str]) -> String {
+    let output = run_cli(wallet, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap().trim().to_owned()
+}
+
+
+fn cli_error(wallet: &Path, args: &[&str]) -> String {
+    let output = run_cli(wallet, args);
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+
+// Reads "<Item> <pub_key> is submitted in transaction <hash>".
+fn submitted(output: &str) -> (PublicKey, Hash) {
+    let words: Vec<_> = output.split_whitespace().collect();
+    (PublicKey::from_hex(words[1]).unwrap(), Hash::from_hex(words[words.len() - 1]).unwrap())
+}